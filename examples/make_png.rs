@@ -7,10 +7,9 @@ use std::path::Path;
 use clap::crate_version;
 use clap::value_t;
 use clap::{App, Arg};
-use png;
 use png::HasParameters;
 
-use crate::piano_keyboard::KeyboardBuilder;
+use crate::piano_keyboard::{KeyboardBuilder, RgbaStyle};
 
 pub fn usage() -> clap::ArgMatches<'static> {
     App::new("piano_keyboard demo")
@@ -30,7 +29,7 @@ pub fn usage() -> clap::ArgMatches<'static> {
                 .long("left-white-key")
                 .takes_value(true)
                 .default_value("24")
-                .help("Select left white key"),
+                .help("Select left key"),
         )
         .arg(
             Arg::with_name("right")
@@ -38,7 +37,7 @@ pub fn usage() -> clap::ArgMatches<'static> {
                 .long("right-white-key")
                 .default_value("35")
                 .takes_value(true)
-                .help("Select right white key"),
+                .help("Select right key"),
         )
         .arg(
             Arg::with_name("no_gaps")
@@ -80,7 +79,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let keyboard = KeyboardBuilder::new()
         .set_width(width as u16)?
-        .set_most_left_right_white_keys(left_key, right_key)?
+        .set_most_left_right_keys(left_key, right_key)?
         .white_black_gap_present(!matches.is_present("no_gaps"))
         .build2d();
 
@@ -88,44 +87,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let path = Path::new(r"keyboard.png");
     let file = File::create(path).unwrap();
-    let ref mut w = BufWriter::new(file);
+    let w = &mut BufWriter::new(file);
 
     let mut encoder = png::Encoder::new(w, width, height);
     encoder.set(png::ColorType::RGBA).set(png::BitDepth::Eight);
     let mut writer = encoder.write_header().unwrap();
 
-    let mut data = vec![0; (4 * width * height) as usize];
-
-    for x in 0..width {
-        for y in 0..height {
-            let i = ((y * width + x) * 4) as usize;
-            data[i] = 150;
-            data[i + 1] = 150;
-            data[i + 2] = 150;
-            data[i + 3] = 255;
-        }
-    }
-
-    for (color, rectangles) in vec![
-        (
-            vec![255, 255, 255, 255],
-            keyboard.white_keys(true).into_iter(),
-        ),
-        (vec![0, 0, 0, 255], keyboard.black_keys().into_iter()),
-    ]
-    .into_iter()
-    {
-        for rect in rectangles.into_iter() {
-            for x in rect.x..(rect.x + rect.width) {
-                for y in rect.y..(rect.y + rect.height) {
-                    let i = ((y as u32 * width + x as u32) * 4) as usize;
-                    for (j, c) in color.iter().enumerate() {
-                        data[i + j] = *c;
-                    }
-                }
-            }
-        }
-    }
+    let data = keyboard.to_rgba(&RgbaStyle::new());
 
     writer.write_image_data(&data).unwrap();
 
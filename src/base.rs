@@ -1,6 +1,7 @@
-///! Base builder dealing only with white keys and key gaps between white keys.
-///
-use crate::KeyboardBuilder;
+//! Base builder dealing only with white keys and key gaps between white keys.
+//!
+use crate::report::{Adjustment, AdjustmentKind, LayoutReport, Target};
+use crate::{KeyboardBuilder, KeyboardError};
 
 pub const KEY_C: u8 = 0;
 //pub const KEY_CIS: u8 = 1;
@@ -80,35 +81,39 @@ pub struct Base {
 }
 
 impl Base {
-    pub fn calculate(kb: &KeyboardBuilder) -> Base {
-        let mut base = Base::default();
-        base.width = kb.width;
+    pub fn calculate(kb: &KeyboardBuilder) -> Result<Base, KeyboardError> {
+        let mut base = Base {
+            width: kb.width,
+            ..Default::default()
+        };
 
         // Derive key gap measure from the given dimensions
-        let key_gap_10um =
-            kb.white_key_height_10um - kb.black_key_height_10um - kb.white_key_wide_height_10um;
+        let key_gap_10um = kb.dims.key_gap_10um();
 
-        base.nr_of_white_keys = (kb.left_white_key..=kb.right_white_key)
+        base.nr_of_white_keys = (kb.left_key..=kb.right_key)
             .filter(|k| KeyboardBuilder::is_white(*k))
             .count() as u16;
 
         // Calculate the total keyboard width.
         // Left and right from the outer keys have a gap, too
-        let keyboard_width_10um = (kb.white_key_wide_width_10um + key_gap_10um)
+        let keyboard_width_10um = (kb.dims.white_key_wide_width_10um + key_gap_10um)
             * base.nr_of_white_keys as u32
             + key_gap_10um;
 
         // Calculate the lower values for key gap and white key
         base.key_gap_min = (key_gap_10um * kb.width as u32 / keyboard_width_10um) as u16;
         base.kw_width_min =
-            (kb.white_key_wide_width_10um * kb.width as u32 / keyboard_width_10um) as u16;
+            (kb.dims.white_key_wide_width_10um * kb.width as u32 / keyboard_width_10um) as u16;
 
         base.kb_width_min =
-            (kb.black_key_width_10um * kb.width as u32 / keyboard_width_10um) as u16;
+            (kb.dims.black_key_width_10um * kb.width as u32 / keyboard_width_10um) as u16;
 
         if base.key_gap_min == 0 {
             base.key_gap_min = 1;
-            base.kw_width_min -= 1;
+            base.kw_width_min = base
+                .kw_width_min
+                .checked_sub(1)
+                .ok_or(KeyboardError::InconsistentLayout("white key too small"))?;
         }
 
         // If the above remainders sum up to more than 1, then kw_width_min should be increased
@@ -126,12 +131,15 @@ impl Base {
             + (base.nr_of_white_keys + 1) * base.key_gap_min;
 
         // Ensure proper result
-        assert!(min_width <= kb.width);
-        assert!(max_width >= kb.width);
+        if min_width > kb.width || max_width < kb.width {
+            return Err(KeyboardError::InconsistentLayout(
+                "white keys and gaps do not match the width",
+            ));
+        }
 
         // Fill the elements
         base.elements.push(Element::IdenticalGap);
-        for key in kb.left_white_key..=kb.right_white_key {
+        for key in kb.left_key..=kb.right_key {
             if KeyboardBuilder::is_white(key) {
                 base.elements.push(Element::IdenticalWhite(key));
                 base.elements.push(Element::IdenticalGap);
@@ -146,7 +154,7 @@ impl Base {
         let mut possible_fgab = false;
         let mut possible_bc_gap = false;
         let mut possible_ef_gap = false;
-        for key in kb.left_white_key..=kb.right_white_key {
+        for key in kb.left_key..=kb.right_key {
             match key % 12 {
                 KEY_C => {
                     base.nr_of_c += 1;
@@ -193,50 +201,52 @@ impl Base {
             }
         }
 
-        base.find_solution();
+        base.find_solution()?;
+
+        Ok(base)
+    }
 
-        base
+    fn width_of(&self, e: &Element) -> u16 {
+        match e {
+            Element::IdenticalWhite(_) => self.identical_key,
+            Element::IdenticalGap => self.identical_gap,
+            Element::GapBC => self.gap_bc,
+            Element::GapEF => self.gap_ef,
+            Element::KeyD(_) => self.width_d,
+            Element::KeyCDE(_) => self.width_cde,
+            Element::KeyFGAB(_) => self.width_fgab,
+            Element::OutterGap => self.outter_gaps,
+            Element::EnlargedOutterLeftKey(_) => self.outter_left_key,
+            Element::EnlargedOutterRightKey(_) => self.outter_right_key,
+        }
     }
 
-    fn current_width(&self) -> (u16, u16) {
+    fn current_width(&self) -> Result<(u16, u16), KeyboardError> {
         // Accumulate width of all elements and return result
-        let w = self
-            .elements
-            .iter()
-            .map(|e| match e {
-                Element::IdenticalWhite(_) => self.identical_key,
-                Element::IdenticalGap => self.identical_gap,
-                Element::GapBC => self.gap_bc,
-                Element::GapEF => self.gap_ef,
-                Element::KeyD(_) => self.width_d,
-                Element::KeyCDE(_) => self.width_cde,
-                Element::KeyFGAB(_) => self.width_fgab,
-                Element::OutterGap => self.outter_gaps,
-                Element::EnlargedOutterLeftKey(_) => self.outter_left_key,
-                Element::EnlargedOutterRightKey(_) => self.outter_right_key,
-            })
-            .sum();
+        let w = self.elements.iter().map(|e| self.width_of(e)).sum();
         if w > self.width {
-            panic!("calculated width should not be bigger than given width");
+            return Err(KeyboardError::InconsistentLayout(
+                "calculated width should not be bigger than given width",
+            ));
         }
-        (w, self.width - w)
+        Ok((w, self.width - w))
     }
 
-    fn find_solution(&mut self) {
+    fn find_solution(&mut self) -> Result<(), KeyboardError> {
         let mut last_delta = 0;
         loop {
-            let (_current, delta) = self.current_width();
+            let (_current, delta) = self.current_width()?;
 
             if delta == 0 {
-                return; // solution already found
+                return Ok(()); // solution already found
             }
 
             // Avoid endless loop
             if delta == last_delta {
-                panic!(
-                    "{:?}\n\n=================\nno solution. remaining delta {}\n=================",
-                    self, delta
-                );
+                return Err(KeyboardError::NoSolution {
+                    width: self.width,
+                    remaining: delta,
+                });
             }
             last_delta = delta;
 
@@ -254,9 +264,9 @@ impl Base {
 
             // If increasing the gap is multiple of cde or fgab groups + 0..4,
             // then increase gap
-            if delta >= self.nr_of_white_keys + 1 {
+            if delta > self.nr_of_white_keys {
                 let rem = delta - self.nr_of_white_keys - 1;
-                if rem % self.nr_of_cde <= 4 || rem % self.nr_of_fgab <= 4 {
+                if self.fits_groups(rem) {
                     self.identical_gap += 1;
                     continue;
                 }
@@ -266,16 +276,15 @@ impl Base {
             // then increase white keys
             if delta >= self.nr_of_white_keys {
                 let rem = delta - self.nr_of_white_keys;
-                if rem % self.nr_of_cde <= 4 || rem % self.nr_of_fgab <= 4 {
+                if self.fits_groups(rem) {
                     self.identical_key += 1;
                     continue;
                 }
             }
 
             // Try to make use of enlarged keys FGAB
-            if delta >= self.nr_of_f + self.nr_of_g + self.nr_of_a + self.nr_of_b
-                && !self.fgab_keys_enlarged
-            {
+            let nr_of_fgab_keys = self.nr_of_f + self.nr_of_g + self.nr_of_a + self.nr_of_b;
+            if delta >= nr_of_fgab_keys && nr_of_fgab_keys > 0 && !self.fgab_keys_enlarged {
                 self.fgab_keys_enlarged = true;
                 for i in 1..self.elements.len() - 1 {
                     let key = match self.elements[i] {
@@ -295,7 +304,8 @@ impl Base {
             }
 
             // Try to make use of enlarged keys CDE
-            if delta >= self.nr_of_c + self.nr_of_d + self.nr_of_e && !self.cde_keys_enlarged {
+            let nr_of_cde_keys = self.nr_of_c + self.nr_of_d + self.nr_of_e;
+            if delta >= nr_of_cde_keys && nr_of_cde_keys > 0 && !self.cde_keys_enlarged {
                 self.cde_keys_enlarged = true;
                 for i in 1..self.elements.len() - 1 {
                     let key = match self.elements[i] {
@@ -334,7 +344,7 @@ impl Base {
             }
 
             // Try to make use of enlarged gap between e and f
-            if delta >= self.nr_of_ef_gaps && !self.ef_gaps_enlarged {
+            if delta >= self.nr_of_ef_gaps && self.nr_of_ef_gaps > 0 && !self.ef_gaps_enlarged {
                 self.ef_gaps_enlarged = true;
                 for i in 3..self.elements.len() - 1 {
                     match self.elements[i] {
@@ -352,7 +362,11 @@ impl Base {
             }
 
             // Try to make use of enlarged key D
-            if delta >= self.nr_of_d && !self.d_key_enlarged && !self.alternating_d_key_enlarged {
+            if delta >= self.nr_of_d
+                && self.has_inner_key_d()
+                && !self.d_key_enlarged
+                && !self.alternating_d_key_enlarged
+            {
                 self.d_key_enlarged = true;
                 for i in 3..self.elements.len() - 1 {
                     let key = match self.elements[i] {
@@ -408,7 +422,7 @@ impl Base {
                         self.outter_left_key += 1;
                         Element::EnlargedOutterLeftKey(key)
                     }
-                    ref el => panic!("Should not happen: {:?}", el),
+                    _ => return Err(KeyboardError::InconsistentLayout("no key at the edge")),
                 };
                 let n = self.elements.len();
                 self.elements[n - 2] = match self.elements[n - 2] {
@@ -428,13 +442,16 @@ impl Base {
                         self.outter_right_key += 1;
                         Element::EnlargedOutterRightKey(key)
                     }
-                    ref el => panic!("Should not happen: {:?}", el),
+                    _ => return Err(KeyboardError::InconsistentLayout("no key at the edge")),
                 };
                 continue;
             }
 
             // Try to make use of alternating enlarged key D
-            if delta >= self.nr_of_d / 2 && !self.d_key_enlarged && !self.alternating_d_key_enlarged
+            if delta >= self.nr_of_d / 2
+                && self.has_inner_key_d()
+                && !self.d_key_enlarged
+                && !self.alternating_d_key_enlarged
             {
                 self.alternating_d_key_enlarged = true;
                 let mut enlarge = delta == self.nr_of_d / 2;
@@ -463,7 +480,21 @@ impl Base {
             }
 
             // Use last resort technique by increasing outter gaps and key
-            if self.outter_left_key != self.outter_right_key {
+            if !self.end_keys_enlarged {
+                // Only the left key is available to take the remaining delta
+                self.elements[1] = match self.elements[1] {
+                    Element::IdenticalWhite(key)
+                    | Element::KeyD(key)
+                    | Element::KeyCDE(key)
+                    | Element::KeyFGAB(key) => {
+                        self.outter_left_key = self.width_of(&self.elements[1]);
+                        Element::EnlargedOutterLeftKey(key)
+                    }
+                    Element::EnlargedOutterLeftKey(key) => Element::EnlargedOutterLeftKey(key),
+                    _ => return Err(KeyboardError::InconsistentLayout("no key at the edge")),
+                };
+                self.outter_left_key += 1;
+            } else if self.outter_left_key != self.outter_right_key {
                 let common = self.outter_left_key.min(self.outter_right_key);
                 self.outter_left_key = common;
                 self.outter_right_key = common;
@@ -473,12 +504,135 @@ impl Base {
             }
         }
     }
-    pub fn is_perfect(&self) -> bool {
-        !self.d_key_enlarged
-            && !self.alternating_d_key_enlarged
-            && !self.end_keys_enlarged
-            && !self.cde_keys_enlarged
-            && !self.fgab_keys_enlarged
+    // The key D is only enlarged, if it is not the most left key
+    fn has_inner_key_d(&self) -> bool {
+        let n = self.elements.len();
+        self.elements.iter().take(n - 1).skip(3).any(|e| {
+            matches!(e, Element::IdenticalWhite(key) | Element::KeyCDE(key) if key % 12 == KEY_D)
+        })
+    }
+    // True, if the remainder can be distributed on the cde or fgab groups.
+    // Short keyboards may not contain any complete group.
+    fn fits_groups(&self, rem: u16) -> bool {
+        (self.nr_of_cde > 0 && rem % self.nr_of_cde <= 4)
+            || (self.nr_of_fgab > 0 && rem % self.nr_of_fgab <= 4)
+    }
+    // The width of a key, if it had not been enlarged as end key
+    fn natural_width(&self, key: u8) -> u16 {
+        match key % 12 {
+            KEY_C | KEY_D | KEY_E if self.cde_keys_enlarged => self.width_cde,
+            KEY_F | KEY_G | KEY_A | KEY_B if self.fgab_keys_enlarged => self.width_fgab,
+            _ => self.identical_key,
+        }
+    }
+    /// The report of all enlarged keys and gaps.
+    /// The black keys are not included, because they are calculated by Top.
+    pub fn report(&self) -> LayoutReport {
+        let key_of = |e: &Element| match e {
+            Element::IdenticalWhite(key)
+            | Element::KeyD(key)
+            | Element::KeyCDE(key)
+            | Element::KeyFGAB(key)
+            | Element::EnlargedOutterLeftKey(key)
+            | Element::EnlargedOutterRightKey(key) => Some(*key),
+            _ => None,
+        };
+        let mut adjustments = vec![];
+        let mut add = |kind, target, pixels: u16| {
+            if pixels > 0 {
+                adjustments.push(Adjustment {
+                    kind,
+                    target,
+                    pixels,
+                })
+            }
+        };
+        for (i, e) in self.elements.iter().enumerate() {
+            // Gaps and keys are alternating, starting and ending with a gap
+            let gap_left_of = || Target::GapLeftOf(key_of(&self.elements[i + 1]).unwrap_or(0));
+            let enlarged = |w: u16, reference: u16| w.saturating_sub(reference);
+            match e {
+                Element::IdenticalWhite(_) | Element::IdenticalGap => (),
+                Element::GapBC => add(
+                    AdjustmentKind::GapsBC,
+                    gap_left_of(),
+                    enlarged(self.gap_bc, self.identical_gap),
+                ),
+                Element::GapEF => add(
+                    AdjustmentKind::GapsEF,
+                    gap_left_of(),
+                    enlarged(self.gap_ef, self.identical_gap),
+                ),
+                Element::OutterGap => {
+                    let target = if i == 0 {
+                        gap_left_of()
+                    } else {
+                        Target::GapRightOf(key_of(&self.elements[i - 1]).unwrap_or(0))
+                    };
+                    add(
+                        AdjustmentKind::OutterGaps,
+                        target,
+                        enlarged(self.outter_gaps, self.identical_gap),
+                    )
+                }
+                Element::KeyD(key) => {
+                    let kind = if self.alternating_d_key_enlarged {
+                        AdjustmentKind::AlternatingKeysD
+                    } else {
+                        AdjustmentKind::KeysD
+                    };
+                    if self.cde_keys_enlarged {
+                        add(
+                            AdjustmentKind::KeysCDE,
+                            Target::Key(*key),
+                            enlarged(self.width_cde, self.identical_key),
+                        );
+                    }
+                    add(
+                        kind,
+                        Target::Key(*key),
+                        enlarged(self.width_d, self.natural_width(*key)),
+                    )
+                }
+                Element::KeyCDE(key) => add(
+                    AdjustmentKind::KeysCDE,
+                    Target::Key(*key),
+                    enlarged(self.width_cde, self.identical_key),
+                ),
+                Element::KeyFGAB(key) => add(
+                    AdjustmentKind::KeysFGAB,
+                    Target::Key(*key),
+                    enlarged(self.width_fgab, self.identical_key),
+                ),
+                Element::EnlargedOutterLeftKey(key) | Element::EnlargedOutterRightKey(key) => {
+                    let natural = self.natural_width(*key);
+                    match key % 12 {
+                        KEY_C | KEY_D | KEY_E if self.cde_keys_enlarged => add(
+                            AdjustmentKind::KeysCDE,
+                            Target::Key(*key),
+                            enlarged(natural, self.identical_key),
+                        ),
+                        KEY_F | KEY_G | KEY_A | KEY_B if self.fgab_keys_enlarged => add(
+                            AdjustmentKind::KeysFGAB,
+                            Target::Key(*key),
+                            enlarged(natural, self.identical_key),
+                        ),
+                        _ => (),
+                    }
+                    add(
+                        AdjustmentKind::EndKeys,
+                        Target::Key(*key),
+                        enlarged(self.width_of(e), natural),
+                    )
+                }
+            }
+        }
+        LayoutReport {
+            white_key_width: self.identical_key,
+            key_gap: self.identical_gap,
+            adjustments,
+            ..Default::default()
+        }
     }
     pub fn get_elements(&self) -> Vec<ResultElement> {
         self.elements
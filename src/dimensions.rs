@@ -0,0 +1,213 @@
+//! Physical dimensions of the keys used as reference for all layout calculations.
+//!
+// The measures are grouped as mm_hundredths
+#![allow(clippy::inconsistent_digit_grouping)]
+use crate::DimensionError;
+
+/// A profile of physical key dimensions.
+///
+/// All measures are in 10 µm. The default profile is derived from this internet image:
+/// ![octave drawing](http://www.rwgiangiulio.com/construction/manual/layout.jpg)
+///
+/// The gap between the keys is not given explicitly, but derived as:
+/// white_key_height - black_key_height - white_key_wide_height
+///
+/// A modified profile should be checked by validate() before use.
+/// This is done by KeyboardBuilder::set_key_dimensions().
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyDimensions {
+    /// Width of the wide (front) part of a white key
+    pub white_key_wide_width_10um: u32,
+    /// Width of the small (back) part of the white keys F and B
+    pub white_key_small_width_fb_10um: u32,
+    /// Width of the small (back) part of the white keys G and A
+    pub white_key_small_width_ga_10um: u32,
+
+    /// Width of a black key
+    pub black_key_width_10um: u32,
+    /// Visible length of a black key
+    pub black_key_height_10um: u32,
+
+    /// Visible length of a white key
+    pub white_key_height_10um: u32,
+    /// Length of the wide (front) part of a white key
+    pub white_key_wide_height_10um: u32,
+}
+impl Default for KeyDimensions {
+    fn default() -> Self {
+        KeyDimensions {
+            // http://www.rwgiangiulio.com/construction/manual/layout.jpg
+            white_key_wide_width_10um: 22_15,
+            // following not needed, because assumption is equally spaced
+            //white_key_small_width_cde_10um: 13_97,
+            white_key_small_width_fb_10um: 12_83,
+            white_key_small_width_ga_10um: 13_08,
+
+            black_key_width_10um: 11_00,
+            black_key_height_10um: 80_00,
+
+            white_key_height_10um: 126_27,
+            white_key_wide_height_10um: 45_00,
+        }
+    }
+}
+impl KeyDimensions {
+    /// Derived gap between two white keys
+    pub fn key_gap_10um(&self) -> u32 {
+        self.white_key_height_10um - self.black_key_height_10um - self.white_key_wide_height_10um
+    }
+    /// Check the profile for consistency.
+    pub fn validate(&self) -> Result<(), DimensionError> {
+        let small_width = self
+            .white_key_small_width_fb_10um
+            .max(self.white_key_small_width_ga_10um);
+        if self.white_key_wide_width_10um == 0
+            || self.white_key_small_width_fb_10um == 0
+            || self.white_key_small_width_ga_10um == 0
+            || self.black_key_width_10um == 0
+            || self.black_key_height_10um == 0
+            || self.white_key_height_10um == 0
+            || self.white_key_wide_height_10um == 0
+        {
+            Err(DimensionError::Zero)
+        } else if self.white_key_wide_width_10um > 100_00 {
+            Err(DimensionError::WhiteKeyTooWide(
+                self.white_key_wide_width_10um,
+            ))
+        } else if self.white_key_height_10um > 1_000_00 {
+            Err(DimensionError::WhiteKeyTooLong(self.white_key_height_10um))
+        } else if self.black_key_height_10um + self.white_key_wide_height_10um
+            >= self.white_key_height_10um
+        {
+            Err(DimensionError::NoKeyGap {
+                black_key_height: self.black_key_height_10um,
+                white_key_wide_height: self.white_key_wide_height_10um,
+                white_key_height: self.white_key_height_10um,
+            })
+        } else if self.key_gap_10um() >= self.white_key_wide_width_10um {
+            Err(DimensionError::KeyGapTooWide {
+                key_gap: self.key_gap_10um(),
+                white_key_width: self.white_key_wide_width_10um,
+            })
+        } else if self.black_key_width_10um >= self.white_key_wide_width_10um {
+            Err(DimensionError::BlackKeyTooWide {
+                black_key_width: self.black_key_width_10um,
+                white_key_width: self.white_key_wide_width_10um,
+            })
+        } else if small_width >= self.white_key_wide_width_10um {
+            Err(DimensionError::SmallPartTooWide {
+                small_width,
+                white_key_width: self.white_key_wide_width_10um,
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// The additional physical dimensions of the keys for a 3d keyboard.
+///
+/// All measures are in 10 µm. The defaults are typical values of an acoustic piano.
+/// The footprint of the keys is given by KeyDimensions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyDimensions3d {
+    /// Height of a white key above the key bed
+    pub white_key_thickness_10um: u32,
+    /// Height of a black key above the surface of the white keys
+    pub black_key_raise_10um: u32,
+    /// Overhang of the white key top at the front
+    pub front_lip_depth_10um: u32,
+    /// Thickness of the overhanging white key top at the front
+    pub front_lip_height_10um: u32,
+    /// Distance of the pivot axis of all keys from the front of the white keys
+    pub pivot_distance_10um: u32,
+}
+impl Default for KeyDimensions3d {
+    fn default() -> Self {
+        KeyDimensions3d {
+            white_key_thickness_10um: 22_00,
+            black_key_raise_10um: 12_00,
+            front_lip_depth_10um: 2_00,
+            front_lip_height_10um: 3_00,
+            pivot_distance_10um: 250_00,
+        }
+    }
+}
+impl KeyDimensions3d {
+    /// Check the profile for consistency with the footprint of the keys.
+    pub fn validate(&self, dims: &KeyDimensions) -> Result<(), DimensionError> {
+        if self.white_key_thickness_10um == 0
+            || self.black_key_raise_10um == 0
+            || self.front_lip_height_10um == 0
+            || self.pivot_distance_10um == 0
+        {
+            Err(DimensionError::Zero)
+        } else if self.front_lip_height_10um >= self.white_key_thickness_10um {
+            Err(DimensionError::LipTooThick {
+                lip_height: self.front_lip_height_10um,
+                white_key_thickness: self.white_key_thickness_10um,
+            })
+        } else if self.front_lip_depth_10um >= dims.white_key_wide_height_10um {
+            Err(DimensionError::LipTooDeep {
+                lip_depth: self.front_lip_depth_10um,
+                white_key_wide_height: dims.white_key_wide_height_10um,
+            })
+        } else if self.pivot_distance_10um <= dims.white_key_height_10um {
+            Err(DimensionError::PivotTooClose {
+                pivot_distance: self.pivot_distance_10um,
+                white_key_height: dims.white_key_height_10um,
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DimensionError, KeyDimensions, KeyDimensions3d};
+
+    #[test]
+    fn test_validate() {
+        assert!(KeyDimensions::default().validate().is_ok());
+        let dims = KeyDimensions {
+            black_key_height_10um: 90_00,
+            ..KeyDimensions::default()
+        };
+        assert!(dims.validate().is_err());
+        let dims = KeyDimensions {
+            black_key_width_10um: 25_00,
+            ..KeyDimensions::default()
+        };
+        assert!(matches!(
+            dims.validate(),
+            Err(DimensionError::BlackKeyTooWide { .. })
+        ));
+        let dims = KeyDimensions {
+            white_key_wide_width_10um: 0,
+            ..KeyDimensions::default()
+        };
+        assert_eq!(dims.validate(), Err(DimensionError::Zero));
+    }
+    #[test]
+    fn test_validate_3d() {
+        let dims = KeyDimensions::default();
+        assert!(KeyDimensions3d::default().validate(&dims).is_ok());
+        let dims_3d = KeyDimensions3d {
+            pivot_distance_10um: 100_00,
+            ..KeyDimensions3d::default()
+        };
+        assert!(matches!(
+            dims_3d.validate(&dims),
+            Err(DimensionError::PivotTooClose { .. })
+        ));
+        let dims_3d = KeyDimensions3d {
+            front_lip_height_10um: 30_00,
+            ..KeyDimensions3d::default()
+        };
+        assert!(matches!(
+            dims_3d.validate(&dims),
+            Err(DimensionError::LipTooThick { .. })
+        ));
+    }
+}
@@ -0,0 +1,267 @@
+//! Errors reported by the KeyboardBuilder.
+//!
+use std::fmt;
+
+/// The reasons for a rejected keyboard configuration.
+///
+/// The offending values are included, so an application can react on them.
+/// E.g. for WidthTooSmall the key range may be reduced until min_width fits.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KeyboardError {
+    /// The number of keys is none of the standard pianos 25/37/49/61/64/73/76/88
+    UnknownStandardPiano(u8),
+    /// The MIDI key code is not in range 0..128
+    KeyOutOfRange(u8),
+    /// The left key is right from the right key
+    KeysSwapped { left: u8, right: u8 },
+    /// The key is required to be a white key
+    NotAWhiteKey(u8),
+    /// The width is too small for the number of keys
+    WidthTooSmall {
+        width: u16,
+        min_width: u16,
+        nr_of_keys: u16,
+    },
+    /// The width is too big. The limit is lower for black keys at the edges.
+    WidthTooBig { width: u16, max_width: u16 },
+    /// The dot ratio is zero or out of the supported range
+    InvalidDotRatio { dot_width: u16, dot_height: u16 },
+    /// The key dimensions are not consistent
+    InvalidKeyDimensions(DimensionError),
+    /// The stretch range for fitting the key lengths is not supported
+    InvalidStretch { min_percent: u16, max_percent: u16 },
+    /// The keyboard cannot be fitted into the box
+    DoesNotFit { max_width: u16, max_height: u16 },
+    /// The layout calculation has not found a solution for the width.
+    /// The remaining pixels could not be distributed.
+    NoSolution { width: u16, remaining: u16 },
+    /// An internal consistency check of the layout calculation has failed.
+    InconsistentLayout(&'static str),
+    /// A multi-manual arrangement needs at least one manual
+    NoManuals,
+    /// The row height of a Jankó keyboard is zero or too big
+    InvalidRowHeight(u16),
+    /// The number of columns or rows of an isomorphic keyboard is zero or too big
+    InvalidGridSize { columns: u16, rows: u16 },
+    /// The white/black pattern of a microtonal keyboard is not valid
+    InvalidKeyPattern(&'static str),
+    /// The stride of a pixel buffer is smaller than one row of the keyboard
+    InvalidStride { stride: usize, min_stride: usize },
+    /// The pixel buffer is too small for the keyboard
+    BufferTooSmall { len: usize, required: usize },
+}
+
+/// The reasons for rejected key dimensions as reported by KeyDimensions::validate().
+/// All values are in 10 µm.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DimensionError {
+    /// All dimensions must be greater than zero
+    Zero,
+    /// The white key width must not exceed 10 cm
+    WhiteKeyTooWide(u32),
+    /// The white key height must not exceed one meter
+    WhiteKeyTooLong(u32),
+    /// Black key height plus white key wide height must be shorter than white key height
+    NoKeyGap {
+        black_key_height: u32,
+        white_key_wide_height: u32,
+        white_key_height: u32,
+    },
+    /// The derived key gap must be smaller than the white key width
+    KeyGapTooWide { key_gap: u32, white_key_width: u32 },
+    /// The black key must be smaller than the white key
+    BlackKeyTooWide {
+        black_key_width: u32,
+        white_key_width: u32,
+    },
+    /// The small parts of the white keys must be smaller than the wide part
+    SmallPartTooWide {
+        small_width: u32,
+        white_key_width: u32,
+    },
+    /// The front lip must be thinner than the white key
+    LipTooThick {
+        lip_height: u32,
+        white_key_thickness: u32,
+    },
+    /// The front lip must be shorter than the wide part of the white key
+    LipTooDeep {
+        lip_depth: u32,
+        white_key_wide_height: u32,
+    },
+    /// The pivot must be behind the visible part of the white key
+    PivotTooClose {
+        pivot_distance: u32,
+        white_key_height: u32,
+    },
+}
+
+impl fmt::Display for KeyboardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyboardError::UnknownStandardPiano(n) => {
+                write!(f, "size {} not a recognized standard size", n)
+            }
+            KeyboardError::KeyOutOfRange(key) => write!(f, "key {} is out of range", key),
+            KeyboardError::KeysSwapped { left, right } => {
+                write!(f, "left key {} right from right key {}", left, right)
+            }
+            KeyboardError::NotAWhiteKey(key) => write!(f, "key {} is not a white key", key),
+            KeyboardError::WidthTooSmall {
+                width,
+                min_width,
+                nr_of_keys,
+            } => write!(
+                f,
+                "Keyboard width too small {} < {} for {} keys",
+                width, min_width, nr_of_keys
+            ),
+            KeyboardError::WidthTooBig { width, max_width } => {
+                write!(f, "Keyboard width {} too big > {}", width, max_width)
+            }
+            KeyboardError::InvalidDotRatio {
+                dot_width,
+                dot_height,
+            } => write!(
+                f,
+                "dot ratio {}:{} is out of supported range",
+                dot_width, dot_height
+            ),
+            KeyboardError::InvalidKeyDimensions(e) => write!(f, "invalid key dimensions: {}", e),
+            KeyboardError::InvalidStretch {
+                min_percent,
+                max_percent,
+            } => write!(
+                f,
+                "stretch range {}%..{}% must include 100% and is limited to 1%..1000%",
+                min_percent, max_percent
+            ),
+            KeyboardError::DoesNotFit {
+                max_width,
+                max_height,
+            } => write!(
+                f,
+                "Keyboard does not fit into {}x{} pixels",
+                max_width, max_height
+            ),
+            KeyboardError::NoSolution { width, remaining } => write!(
+                f,
+                "no solution for width {}. remaining delta {}",
+                width, remaining
+            ),
+            KeyboardError::InconsistentLayout(reason) => {
+                write!(f, "inconsistent layout: {}", reason)
+            }
+            KeyboardError::NoManuals => write!(f, "no manuals added"),
+            KeyboardError::InvalidRowHeight(height) => {
+                write!(f, "row height {} is out of supported range", height)
+            }
+            KeyboardError::InvalidGridSize { columns, rows } => write!(
+                f,
+                "grid of {} columns and {} rows is out of supported range",
+                columns, rows
+            ),
+            KeyboardError::InvalidKeyPattern(reason) => {
+                write!(f, "invalid key pattern: {}", reason)
+            }
+            KeyboardError::InvalidStride { stride, min_stride } => write!(
+                f,
+                "stride {} is smaller than the row length {}",
+                stride, min_stride
+            ),
+            KeyboardError::BufferTooSmall { len, required } => write!(
+                f,
+                "buffer of {} bytes is smaller than the required {} bytes",
+                len, required
+            ),
+        }
+    }
+}
+
+impl fmt::Display for DimensionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DimensionError::Zero => write!(f, "all key dimensions must be greater than zero"),
+            DimensionError::WhiteKeyTooWide(_) => {
+                write!(f, "white key width must not exceed 10 cm")
+            }
+            DimensionError::WhiteKeyTooLong(_) => {
+                write!(f, "white key height must not exceed one meter")
+            }
+            DimensionError::NoKeyGap {
+                black_key_height,
+                white_key_wide_height,
+                white_key_height,
+            } => write!(
+                f,
+                "black key height {} plus white key wide height {} must be shorter than white key height {}",
+                black_key_height, white_key_wide_height, white_key_height
+            ),
+            DimensionError::KeyGapTooWide {
+                key_gap,
+                white_key_width,
+            } => write!(
+                f,
+                "derived key gap {} must be smaller than white key width {}",
+                key_gap, white_key_width
+            ),
+            DimensionError::BlackKeyTooWide {
+                black_key_width,
+                white_key_width,
+            } => write!(
+                f,
+                "black key width {} must be smaller than white key width {}",
+                black_key_width, white_key_width
+            ),
+            DimensionError::SmallPartTooWide {
+                small_width,
+                white_key_width,
+            } => write!(
+                f,
+                "small width {} of white keys must be smaller than white key width {}",
+                small_width, white_key_width
+            ),
+            DimensionError::LipTooThick {
+                lip_height,
+                white_key_thickness,
+            } => write!(
+                f,
+                "front lip height {} must be smaller than white key thickness {}",
+                lip_height, white_key_thickness
+            ),
+            DimensionError::LipTooDeep {
+                lip_depth,
+                white_key_wide_height,
+            } => write!(
+                f,
+                "front lip depth {} must be smaller than white key wide height {}",
+                lip_depth, white_key_wide_height
+            ),
+            DimensionError::PivotTooClose {
+                pivot_distance,
+                white_key_height,
+            } => write!(
+                f,
+                "pivot distance {} must be bigger than white key height {}",
+                pivot_distance, white_key_height
+            ),
+        }
+    }
+}
+
+impl std::error::Error for KeyboardError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            KeyboardError::InvalidKeyDimensions(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl std::error::Error for DimensionError {}
+
+impl From<DimensionError> for KeyboardError {
+    fn from(e: DimensionError) -> Self {
+        KeyboardError::InvalidKeyDimensions(e)
+    }
+}
@@ -0,0 +1,252 @@
+//! Fitting of a keyboard into a bounding box.
+//!
+//! The width along the key axis is fitted pixel exact by the solver, while the height
+//! of the keyboard results from the proportions of the keys. For a given box either
+//! the width is reduced until the height fits, or the key lengths are stretched.
+use crate::{KeyboardBuilder, KeyboardError};
+
+/// How to fit a keyboard into a bounding box.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FitStrategy {
+    /// Keep the proportions of the keys and reduce the width until the height fits.
+    ShrinkWidth,
+    /// Use the full width and stretch or compress the length of the white and black keys
+    /// to the height of the box. The stretch is limited to min_percent..=max_percent.
+    /// If compressing by min_percent is not sufficient, the width is reduced in addition.
+    StretchLength { min_percent: u16, max_percent: u16 },
+}
+
+/// The result of fitting a keyboard into a bounding box as reported by Keyboard2d::fit().
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Fit {
+    /// The strategy requested
+    pub strategy: FitStrategy,
+    /// Is true, if the width along the key axis is smaller than the box
+    pub shrunk: bool,
+    /// Length of the keys in percent of the natural length (rounded)
+    pub stretch_percent: u16,
+}
+
+impl KeyboardBuilder {
+    /// Fit the keyboard into a box of max_width x max_height pixels using the given strategy.
+    ///
+    /// For a vertical keyboard the key axis is along the height of the box.
+    /// The fit is calculated for the current settings, so key range, orientation,
+    /// dot ratio and key dimensions should be set before. A later set_width() removes the fit.
+    pub fn fit_into(
+        mut self,
+        max_width: u16,
+        max_height: u16,
+        strategy: FitStrategy,
+    ) -> Result<KeyboardBuilder, KeyboardError> {
+        let (length, depth) = if self.orientation == crate::Orientation::Horizontal {
+            (max_width, max_height)
+        } else {
+            (max_height, max_width)
+        };
+        if let FitStrategy::StretchLength {
+            min_percent,
+            max_percent,
+        } = strategy
+        {
+            if !(1..=100).contains(&min_percent) || !(100..=1000).contains(&max_percent) {
+                return Err(KeyboardError::InvalidStretch {
+                    min_percent,
+                    max_percent,
+                });
+            }
+        }
+        // The box may be wider than the maximum width, e.g. for a single key
+        self = match self.clone().set_width(length) {
+            Err(KeyboardError::WidthTooBig { max_width, .. }) => self.set_width(max_width)?,
+            result => result?,
+        };
+        let max_length = self.width;
+
+        if let FitStrategy::StretchLength {
+            min_percent,
+            max_percent,
+        } = strategy
+        {
+            // The gaps are not stretched, so the stretch is determined from the key lengths
+            let natural = self.depth()?;
+            let gaps = self.clone().stretched(0).depth()?;
+            let min_1024 = min_percent as u32 * 1024 / 100;
+            let max_1024 = max_percent as u32 * 1024 / 100;
+            if natural <= gaps {
+                // The keys have no length to be stretched
+                return Err(KeyboardError::DoesNotFit {
+                    max_width,
+                    max_height,
+                });
+            }
+            let stretch_1024 = if depth <= gaps {
+                min_1024
+            } else {
+                ((depth - gaps) as u32 * 1024 / (natural - gaps) as u32).clamp(min_1024, max_1024)
+            };
+            // Rounding may exceed the box
+            self = self.stretched(stretch_1024);
+            while self.depth()? > depth && self.stretch_1024 > min_1024 as u16 {
+                let stretch_1024 = self.stretch_1024 - 1;
+                self = self.stretched(stretch_1024 as u32);
+            }
+        }
+
+        let natural = self.depth()?;
+        if natural > depth {
+            let stretch_1024 = self.stretch_1024 as u32;
+            let with_width = |width: u16| {
+                self.clone()
+                    .set_width(width)
+                    .map(|kb| kb.stretched(stretch_1024))
+                    .map_err(|_| KeyboardError::DoesNotFit {
+                        max_width,
+                        max_height,
+                    })
+            };
+            // The depth is about proportional to the width, but not exactly due to rounding.
+            // So start with an estimate and go down until it fits, then up as long as it fits.
+            let mut width = (max_length as u32 * depth as u32 / natural as u32) as u16;
+            let mut fitted = with_width(width)?;
+            while fitted.depth()? > depth {
+                width -= 1;
+                fitted = with_width(width)?;
+            }
+            while width < max_length - 1 {
+                let candidate = with_width(width + 1)?;
+                if candidate.depth()? > depth {
+                    break;
+                }
+                width += 1;
+                fitted = candidate;
+            }
+            self = fitted;
+        }
+
+        self.fit = Some(Fit {
+            strategy,
+            shrunk: self.width < length,
+            stretch_percent: ((self.stretch_1024 as u32 * 100 + 512) / 1024) as u16,
+        });
+        Ok(self)
+    }
+    fn stretched(mut self, stretch_1024: u32) -> KeyboardBuilder {
+        self.stretch_1024 = stretch_1024 as u16;
+        self
+    }
+    // The size of the keyboard perpendicular to the key axis
+    fn depth(&self) -> Result<u16, KeyboardError> {
+        let (solver, _) = self.solver()?;
+        Ok(solver.layout()?.1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{FitStrategy, KeyboardBuilder, KeyboardError, Orientation};
+
+    #[test]
+    fn test_shrink_width() {
+        for height in [30, 50, 80, 120, 200] {
+            let keyboard = KeyboardBuilder::new()
+                .standard_piano(61)
+                .unwrap()
+                .fit_into(1000, height, FitStrategy::ShrinkWidth)
+                .unwrap()
+                .build2d();
+            let fit = keyboard.fit().unwrap();
+            assert!(keyboard.width <= 1000);
+            assert!(keyboard.height <= height);
+            assert_eq!(fit.shrunk, keyboard.width < 1000);
+            assert_eq!(fit.stretch_percent, 100);
+            // One pixel more does not fit anymore
+            if fit.shrunk {
+                let wider = KeyboardBuilder::new()
+                    .standard_piano(61)
+                    .unwrap()
+                    .set_width(keyboard.width + 1)
+                    .unwrap()
+                    .build2d();
+                assert!(wider.height > height);
+            }
+        }
+        assert!(KeyboardBuilder::new()
+            .fit_into(1000, 2, FitStrategy::ShrinkWidth)
+            .is_err());
+    }
+    #[test]
+    fn test_stretch_length() {
+        let strategy = FitStrategy::StretchLength {
+            min_percent: 50,
+            max_percent: 200,
+        };
+        for height in [40, 60, 100, 150, 300] {
+            let keyboard = KeyboardBuilder::new()
+                .standard_piano(49)
+                .unwrap()
+                .set_orientation(Orientation::VerticalLowAtBottom)
+                .fit_into(height, 800, strategy)
+                .unwrap()
+                .build2d();
+            let fit = keyboard.fit().unwrap();
+            assert!(keyboard.width <= height);
+            assert!(keyboard.height <= 800);
+            assert!((50..=200).contains(&fit.stretch_percent));
+            if !fit.shrunk && fit.stretch_percent < 200 {
+                // The box is filled up to rounding
+                assert!(keyboard.width + 3 >= height);
+            }
+        }
+        assert!(KeyboardBuilder::new()
+            .fit_into(
+                800,
+                100,
+                FitStrategy::StretchLength {
+                    min_percent: 110,
+                    max_percent: 200
+                }
+            )
+            .is_err());
+    }
+    #[test]
+    fn test_fit_limits() {
+        // The keys are too flat to have any length
+        assert_eq!(
+            KeyboardBuilder::new()
+                .set_dot_ratio(1, 60)
+                .unwrap()
+                .fit_into(
+                    264,
+                    100,
+                    FitStrategy::StretchLength {
+                        min_percent: 50,
+                        max_percent: 200
+                    }
+                )
+                .err(),
+            Some(KeyboardError::DoesNotFit {
+                max_width: 264,
+                max_height: 100
+            })
+        );
+
+        // The box is wider than the maximum width of a single key
+        let builder = KeyboardBuilder::new()
+            .set_most_left_right_keys(60, 60)
+            .unwrap();
+        let max_width = match builder.clone().set_width(60000) {
+            Err(KeyboardError::WidthTooBig { max_width, .. }) => max_width,
+            other => panic!("unexpected {:?}", other.err()),
+        };
+        let keyboard = builder
+            .fit_into(60000, 60000, FitStrategy::ShrinkWidth)
+            .unwrap()
+            .build2d();
+        assert!(keyboard.width <= max_width);
+        assert!(keyboard.height <= 60000);
+        assert!(keyboard.fit().unwrap().shrunk);
+    }
+}
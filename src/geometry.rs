@@ -0,0 +1,502 @@
+//! Fractional keyboard geometry with the true proportions of the key dimensions.
+//!
+//! In contrast to the pixel accurate layout no compromises are needed here.
+//! The layout is calculated in millimetres for a horizontal keyboard and then
+//! scaled to the requested width, cropped and transformed like the pixel layout.
+use crate::base::{KEY_A, KEY_B, KEY_C, KEY_D, KEY_E, KEY_F, KEY_G};
+use crate::microtonal;
+use crate::transform::Transform;
+use crate::{KeyboardBuilder, Orientation};
+
+/// The fractional counterpart of Rectangle.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RectangleF32 {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+impl RectangleF32 {
+    /// Returns true, if the point at x/y is part of the rectangle.
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+    fn crop(&self, start: f32, end: f32) -> RectangleF32 {
+        let x = self.x.max(start).min(end);
+        let x_end = (self.x + self.width).min(end).max(x);
+        RectangleF32 {
+            x,
+            width: x_end - x,
+            ..self.clone()
+        }
+    }
+    fn scale(&self, offset: f32, factor_x: f32, factor_y: f32) -> RectangleF32 {
+        RectangleF32 {
+            x: (self.x - offset) * factor_x,
+            y: self.y * factor_y,
+            width: self.width * factor_x,
+            height: self.height * factor_y,
+        }
+    }
+}
+
+/// The fractional counterpart of Element with the same structure.
+#[derive(Clone, Debug)]
+pub enum ElementF32 {
+    WhiteKey {
+        wide: RectangleF32,
+        small: RectangleF32,
+        blind: Option<RectangleF32>,
+        key: u8,
+    },
+    BlackKey(RectangleF32, u8),
+}
+impl ElementF32 {
+    /// The MIDI key code of this element.
+    pub fn key(&self) -> u8 {
+        match self {
+            ElementF32::WhiteKey { key, .. } => *key,
+            ElementF32::BlackKey(_, key) => *key,
+        }
+    }
+    pub fn is_white(&self) -> bool {
+        match self {
+            ElementF32::WhiteKey { .. } => true,
+            ElementF32::BlackKey(..) => false,
+        }
+    }
+    pub(crate) fn rects(&self) -> Vec<&RectangleF32> {
+        match self {
+            ElementF32::WhiteKey {
+                wide, small, blind, ..
+            } => {
+                let mut rects = vec![wide, small];
+                rects.extend(blind.iter());
+                rects
+            }
+            ElementF32::BlackKey(r, _) => vec![r],
+        }
+    }
+    fn map_rects(self, f: impl Fn(&RectangleF32) -> RectangleF32) -> ElementF32 {
+        match self {
+            ElementF32::WhiteKey {
+                wide,
+                small,
+                blind,
+                key,
+            } => ElementF32::WhiteKey {
+                wide: f(&wide),
+                small: f(&small),
+                blind: blind.as_ref().map(&f),
+                key,
+            },
+            ElementF32::BlackKey(r, key) => ElementF32::BlackKey(f(&r), key),
+        }
+    }
+}
+
+/// The keyboard with fractional geometry as returned by KeyboardBuilder::build2d_f32().
+///
+/// The width equals the width given to the builder, but the units can be chosen freely.
+/// The size of one unit in millimetres is given by unit_mm.
+pub struct Keyboard2dF32 {
+    pub left_key: u8,
+    pub right_key: u8,
+    pub width: f32,
+    pub height: f32,
+    /// Size of one unit along the key axis in millimetres.
+    /// Perpendicular to the key axis this is scaled by the dot ratio.
+    pub unit_mm: f32,
+    elements: Vec<ElementF32>,
+    pub(crate) blinds: Vec<RectangleF32>,
+}
+impl Keyboard2dF32 {
+    /// The sequence is from low to high keys like Keyboard2d::iter()
+    pub fn iter(&self) -> std::slice::Iter<'_, ElementF32> {
+        self.elements.iter()
+    }
+    /// Retrieve the element for a given MIDI key code.
+    pub fn element(&self, key: u8) -> Option<&ElementF32> {
+        if key < self.left_key {
+            return None;
+        }
+        self.elements.get((key - self.left_key) as usize)
+    }
+    /// All white key rectangles - with or without blind.
+    pub fn white_keys(&self, blind_as_white: bool) -> Vec<RectangleF32> {
+        let mut rects = vec![];
+        for el in self.elements.iter() {
+            if let ElementF32::WhiteKey {
+                wide, small, blind, ..
+            } = el
+            {
+                rects.push(wide.clone());
+                rects.push(small.clone());
+                if blind_as_white {
+                    rects.extend(blind.iter().cloned());
+                }
+            }
+        }
+        if blind_as_white {
+            rects.extend(self.blinds.iter().cloned());
+        }
+        rects
+    }
+    /// All black key rectangles.
+    pub fn black_keys(&self) -> Vec<RectangleF32> {
+        let mut rects = vec![];
+        for el in self.elements.iter() {
+            if let ElementF32::BlackKey(r, _) = el {
+                rects.push(r.clone());
+            }
+        }
+        rects
+    }
+}
+
+// Position of the small part and the black keys right of it relative to the white key.
+// The black keys are given by start and width.
+pub(crate) struct KeyTop {
+    pub(crate) small_start: f32,
+    pub(crate) small_width: f32,
+    pub(crate) blacks: Vec<(f32, f32)>,
+}
+
+impl KeyboardBuilder {
+    /// Build the keyboard with fractional geometry instead of pixels.
+    ///
+    /// All settings of the builder are used, but the keys keep the exact proportions
+    /// of the key dimensions, because no rounding is needed.
+    pub fn build2d_f32(self) -> Keyboard2dF32 {
+        let mm = |v: u32| v as f32 / 100.0;
+        let d = &self.dims;
+        let w = mm(d.white_key_wide_width_10um);
+        let g = mm(d.key_gap_10um());
+        let b = mm(d.black_key_width_10um);
+        let black_gap = if self.need_black_gap { g } else { 0.0 };
+        let stretch = self.stretch_1024 as f32 / 1024.0;
+        let black_height = mm(d.black_key_height_10um) * stretch;
+        let wide_height = mm(d.white_key_wide_height_10um) * stretch;
+
+        // c,d,e share the width for three white keys with two black keys and four gaps.
+        // Like in the pixel layout, the gaps next to the black keys depend on black_gap.
+        // f,g,a,b are split according to the small widths of the white keys.
+        let cde = ((3.0 * w + 2.0 * g - 2.0 * b - 4.0 * black_gap) / 3.0).max(0.0);
+        let fgab_white = (4.0 * w + 3.0 * g - 3.0 * b - 6.0 * black_gap).max(0.0);
+        let fb_10um = d.white_key_small_width_fb_10um as f32;
+        let ga_10um = d.white_key_small_width_ga_10um as f32;
+        let fb = fgab_white * fb_10um / (fb_10um + ga_10um) / 2.0;
+        let ga = fgab_white * ga_10um / (fb_10um + ga_10um) / 2.0;
+        // The offset of the next small part relative to the next white key
+        let shift = b + 2.0 * black_gap - w - g;
+        let small_min = mm(d
+            .white_key_small_width_fb_10um
+            .min(d.white_key_small_width_ga_10um)
+            / 2);
+        // The key top of a white key, None for a black key
+        let top_of = |key: u8| {
+            if !self.pattern.is_twelve_tone() {
+                if !self.is_white_key(key) {
+                    return None;
+                }
+                return Some(microtonal::top_f32(
+                    &self.pattern,
+                    key,
+                    w,
+                    g,
+                    b,
+                    black_gap,
+                    small_min,
+                ));
+            }
+            let (small_start, small_width, has_black) = match key % 12 {
+                KEY_C => (0.0, cde, true),
+                KEY_D => (cde + shift, cde, true),
+                KEY_E => (2.0 * (cde + shift), cde, false),
+                KEY_F => (0.0, fb, true),
+                KEY_G => (fb + shift, ga, true),
+                KEY_A => (fb + ga + 2.0 * shift, ga, true),
+                KEY_B => (fb + 2.0 * ga + 3.0 * shift, fb, false),
+                _ => return None,
+            };
+            Some(KeyTop {
+                small_start,
+                small_width,
+                blacks: if has_black {
+                    vec![(small_start + small_width + black_gap, b)]
+                } else {
+                    vec![]
+                },
+            })
+        };
+
+        let (left, right) = self.white_range();
+        let small_y = g;
+        let small_height = black_gap + black_height;
+        let wide_y = g + black_gap + black_height;
+        let depth = 2.0 * g + black_gap + black_height + wide_height;
+
+        let mut elements = vec![];
+        let mut x = g;
+        for key in left..=right {
+            let top = match top_of(key) {
+                Some(top) => top,
+                None => continue,
+            };
+            let mut small_width = top.small_width;
+            let mut blind = None;
+            if key == left && top.small_start > 0.0 {
+                blind = Some(RectangleF32 {
+                    x,
+                    y: small_y,
+                    width: top.small_start,
+                    height: small_height,
+                });
+            }
+            let small_end = top.small_start + top.small_width;
+            if key == right && small_end < w {
+                if blind.is_none() {
+                    blind = Some(RectangleF32 {
+                        x: x + small_end,
+                        y: small_y,
+                        width: w - small_end,
+                        height: small_height,
+                    });
+                } else {
+                    // Like the pixel layout, a single white key extends its small part
+                    small_width = w - top.small_start;
+                }
+            }
+            elements.push(ElementF32::WhiteKey {
+                wide: RectangleF32 {
+                    x,
+                    y: wide_y,
+                    width: w,
+                    height: wide_height,
+                },
+                small: RectangleF32 {
+                    x: x + top.small_start,
+                    y: small_y,
+                    width: small_width,
+                    height: small_height,
+                },
+                blind,
+                key,
+            });
+            if key < right {
+                for (j, (black_start, black_width)) in top.blacks.iter().enumerate() {
+                    let rect = RectangleF32 {
+                        x: x + black_start,
+                        y: small_y,
+                        width: *black_width,
+                        height: black_height,
+                    };
+                    elements.push(ElementF32::BlackKey(rect, key + 1 + j as u8));
+                }
+            }
+            x += w + g;
+        }
+
+        // Crop one gap outside of black keys at the edges
+        let start = if left != self.left_key {
+            elements[1].rects()[0].x - g
+        } else {
+            0.0
+        };
+        let end = if right != self.right_key {
+            let r = elements[elements.len() - 2].rects()[0];
+            r.x + r.width + g
+        } else {
+            x
+        };
+
+        let factor_x = self.width as f32 / (end - start);
+        let dot_ratio = if self.orientation == Orientation::Horizontal {
+            self.dot_ratio_1024 as f32 / 1024.0
+        } else {
+            1024.0 / self.dot_ratio_1024 as f32
+        };
+        let factor_y = factor_x / dot_ratio;
+        let length = self.width as f32;
+        let depth = depth * factor_y;
+        let transform = Transform::new(self.orientation, 0, 0)
+            .mirrored(self.mirror_horizontal, self.mirror_vertical);
+        let place = |r: &RectangleF32| {
+            transform.rect_f32(
+                &r.crop(start, end).scale(start, factor_x, factor_y),
+                length,
+                depth,
+            )
+        };
+
+        let mut blinds = vec![];
+        let mut visible = vec![];
+        for el in elements.into_iter() {
+            if (self.left_key..=self.right_key).contains(&el.key()) {
+                visible.push(el.map_rects(place));
+            } else {
+                blinds.extend(
+                    el.rects()
+                        .into_iter()
+                        .map(place)
+                        .filter(|r| r.width > 0.0 && r.height > 0.0),
+                );
+            }
+        }
+
+        let (width, height) = if transform.is_vertical() {
+            (depth, length)
+        } else {
+            (length, depth)
+        };
+        Keyboard2dF32 {
+            left_key: self.left_key,
+            right_key: self.right_key,
+            width,
+            height,
+            unit_mm: 1.0 / factor_x,
+            elements: visible,
+            blinds,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::report::AdjustmentKind;
+    use crate::{ElementF32, KeyboardBuilder, Orientation};
+
+    #[test]
+    fn test_proportions() {
+        let keyboard = KeyboardBuilder::new()
+            .standard_piano(88)
+            .unwrap()
+            .set_width(1000)
+            .unwrap()
+            .build2d_f32();
+        assert_eq!(keyboard.width, 1000.0);
+        assert_eq!(keyboard.iter().count(), 88);
+        // 52 white keys of 22.15mm plus 53 gaps
+        let gap = 126.27 - 80.0 - 45.0;
+        let length_mm = 52.0 * 22.15 + 53.0 * gap;
+        assert!((keyboard.unit_mm * 1000.0 - length_mm).abs() < 0.01);
+        for el in keyboard.iter() {
+            if let ElementF32::WhiteKey { wide, .. } = el {
+                assert!((wide.width * keyboard.unit_mm - 22.15).abs() < 0.001);
+            }
+        }
+        // The octave c4..b4 matches the reference drawing
+        let c4 = keyboard.element(60).unwrap();
+        let b4 = keyboard.element(71).unwrap();
+        if let (ElementF32::WhiteKey { wide: c, .. }, ElementF32::WhiteKey { wide: b, .. }) =
+            (c4, b4)
+        {
+            let octave = (b.x + b.width - c.x) * keyboard.unit_mm;
+            assert!((octave - (7.0 * 22.15 + 6.0 * gap)).abs() < 0.01);
+        }
+    }
+    #[test]
+    fn test_close_to_pixels() {
+        for (left, right) in [(21, 108), (48, 72), (49, 70), (50, 55), (60, 60)] {
+            for width in (400..3000).step_by(53) {
+                for vertical in [false, true] {
+                    let mut builder = KeyboardBuilder::new()
+                        .set_most_left_right_keys(left, right)
+                        .unwrap()
+                        .set_width(width)
+                        .unwrap();
+                    if vertical {
+                        builder = builder
+                            .set_orientation(Orientation::VerticalLowAtBottom)
+                            .set_mirror(true, false);
+                    }
+                    check_close_to_pixels(builder, vertical);
+                }
+            }
+        }
+    }
+    fn check_close_to_pixels(builder: KeyboardBuilder, vertical: bool) {
+        let keyboard = builder.clone().build2d();
+        let keyboard_f32 = builder.build2d_f32();
+        // Start and length along the keys resp. in depth
+        let along = |x: f32, y: f32, w: f32, h: f32| if vertical { (y, h) } else { (x, w) };
+        let depth = |x: f32, y: f32, w: f32, h: f32| if vertical { (x, w) } else { (y, h) };
+        let size = along(
+            keyboard.width as f32,
+            keyboard.height as f32,
+            keyboard_f32.width,
+            keyboard_f32.height,
+        );
+        assert_eq!(size.0, size.1);
+        // The key lengths derive from the white key width, rounded together with the gaps
+        let report = keyboard.report();
+        let (length, length_f32) = depth(
+            keyboard.width as f32,
+            keyboard.height as f32,
+            keyboard_f32.width,
+            keyboard_f32.height,
+        );
+        let scale = length / length_f32;
+        assert!((scale - 1.0).abs() < 1.5 / report.white_key_width as f32);
+
+        let black_f32 = keyboard_f32
+            .iter()
+            .find(|el| !el.is_white())
+            .map(|el| {
+                let r = el.rects()[0];
+                along(r.x, r.y, r.width, r.height).1
+            })
+            .unwrap_or(report.black_key_width as f32);
+        let black_error = (report.black_key_width as f32 - black_f32).abs();
+        for (el, el_f32) in keyboard.iter().zip(keyboard_f32.iter()) {
+            assert_eq!(el.key(), el_f32.key());
+            // Enlarged keys and gaps within an octave shift the edges,
+            // enlarged outter gaps and end keys all of them.
+            let enlarged: u16 = report
+                .adjustments()
+                .iter()
+                .filter(|a| {
+                    a.kind == AdjustmentKind::OutterGaps
+                        || a.kind == AdjustmentKind::EndKeys
+                        || (a.target.key() as i16 - el.key() as i16).abs() < 12
+                })
+                .map(|a| a.pixels)
+                .sum();
+            // Rounding of the key tops and the black keys moves an edge by up to 2.5 pixels
+            let tolerance = 2.5 + enlarged as f32 + 3.0 * black_error;
+            let rects = el.rects();
+            let rects_f32 = el_f32.rects();
+            assert_eq!(rects.len(), rects_f32.len());
+            for (r, r_f32) in rects.iter().zip(rects_f32) {
+                let (x, y, w, h) = (r.x as f32, r.y as f32, r.width as f32, r.height as f32);
+                let (start, len) = along(x, y, w, h);
+                let (start_f32, len_f32) = along(r_f32.x, r_f32.y, r_f32.width, r_f32.height);
+                assert!((start - start_f32).abs() < tolerance);
+                assert!((start + len - start_f32 - len_f32).abs() < tolerance);
+                let (start, len) = depth(x, y, w, h);
+                let (start_f32, len_f32) = depth(r_f32.x, r_f32.y, r_f32.width, r_f32.height);
+                assert!((start - start_f32 * scale).abs() < 1.5);
+                assert!((start + len - (start_f32 + len_f32) * scale).abs() < 1.5);
+            }
+        }
+    }
+    #[test]
+    fn test_black_gap() {
+        for gap_present in [true, false] {
+            let builder = KeyboardBuilder::new()
+                .set_most_left_right_keys(60, 71)
+                .unwrap()
+                .set_width(2000)
+                .unwrap()
+                .white_black_gap_present(gap_present);
+            let keyboard = builder.clone().build2d();
+            let keyboard_f32 = builder.build2d_f32();
+            for (el, el_f32) in keyboard.iter().zip(keyboard_f32.iter()) {
+                for (r, r_f32) in el.rects().iter().zip(el_f32.rects()) {
+                    assert!((r.x as f32 - r_f32.x).abs() < 4.0);
+                    assert!((r.width as f32 - r_f32.width).abs() < 4.0);
+                }
+            }
+        }
+    }
+}
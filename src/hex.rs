@@ -0,0 +1,288 @@
+//! Hexagonal isomorphic keyboards like Wicki-Hayden or the Harmonic Table.
+//!
+//! The hexagons are pointy-top and arranged in rows, with every odd row shifted
+//! right by half a hexagon. Row 0 is at the bottom. The note of a hexagon is
+//! derived from the note of the lower left hexagon and two generator intervals:
+//! one step to the right and one step to the upper right neighbour.
+//! So every chord or scale has the same shape in any key.
+//!
+//! Like for the piano, the width is filled pixel exactly. The height follows
+//! from the regular hexagon shape.
+use crate::{Hit, KeyboardBuilder, KeyboardError};
+
+/// One key of the isomorphic keyboard.
+///
+/// A note can be present on several hexagons.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hexagon {
+    /// The MIDI key code
+    pub key: u8,
+    pub column: u16,
+    /// Row 0 is the bottom row
+    pub row: u16,
+    /// The vertices in clockwise order as seen on the screen starting with the top corner.
+    /// The vertices are at the corners of the pixels like for Keyboard2d::outline().
+    pub points: [(u16, u16); 6],
+}
+impl Hexagon {
+    /// White or black according to the traditional keyboard
+    pub fn is_white(&self) -> bool {
+        KeyboardBuilder::is_white(self.key)
+    }
+    /// Returns true, if the center of the pixel at x/y is inside the hexagon.
+    pub fn contains(&self, x: u16, y: u16) -> bool {
+        // Doubled coordinates, so the pixel center is integer
+        let (px, py) = (2 * x as i64 + 1, 2 * y as i64 + 1);
+        (0..6).all(|i| {
+            let (x0, y0) = self.points[i];
+            let (x1, y1) = self.points[(i + 1) % 6];
+            let (x0, y0, x1, y1) = (2 * x0 as i64, 2 * y0 as i64, 2 * x1 as i64, 2 * y1 as i64);
+            (x1 - x0) * (py - y0) - (y1 - y0) * (px - x0) >= 0
+        })
+    }
+}
+
+/// The isomorphic keyboard as returned by HexBuilder::build().
+pub struct HexKeyboard {
+    pub width: u16,
+    pub height: u16,
+    hexagons: Vec<Hexagon>,
+}
+impl HexKeyboard {
+    /// The sequence is row by row from the bottom, within a row from left to right.
+    pub fn iter(&self) -> std::slice::Iter<'_, Hexagon> {
+        self.hexagons.iter()
+    }
+    /// All hexagons playing the given MIDI key code.
+    pub fn hexagons_of(&self, key: u8) -> impl Iterator<Item = &Hexagon> {
+        self.hexagons.iter().filter(move |h| h.key == key)
+    }
+    /// Determine the key under the pixel at x/y.
+    pub fn key_at(&self, x: u16, y: u16) -> Hit {
+        if x >= self.width || y >= self.height {
+            return Hit::Outside;
+        }
+        self.hexagons
+            .iter()
+            .find(|h| h.contains(x, y))
+            .map(|h| Hit::Key(h.key))
+            .unwrap_or(Hit::Gap)
+    }
+}
+
+/// The builder for a hexagonal isomorphic keyboard.
+#[derive(Clone)]
+pub struct HexBuilder {
+    step_right: i8,
+    step_up_right: i8,
+    base_key: u8,
+    columns: u16,
+    rows: u16,
+    width: u16,
+}
+impl HexBuilder {
+    /// The generator intervals are given in semitones.
+    /// Hexagons with notes outside of the MIDI range are left out.
+    pub fn new(step_right: i8, step_up_right: i8) -> HexBuilder {
+        HexBuilder {
+            step_right,
+            step_up_right,
+            base_key: 48,
+            columns: 12,
+            rows: 8,
+            width: 640,
+        }
+    }
+    /// Wicki-Hayden: whole tones along the rows and a fifth to the upper right
+    pub fn wicki_hayden() -> HexBuilder {
+        HexBuilder::new(2, 7)
+    }
+    /// Harmonic Table rotated, so the fifths are along the rows.
+    /// The upper right neighbour is a major third, the upper left one a minor third lower.
+    pub fn harmonic_table() -> HexBuilder {
+        HexBuilder::new(7, 4)
+    }
+    /// The MIDI key code of the hexagon in the lower left corner.
+    pub fn set_base_key(mut self, key: u8) -> Result<HexBuilder, KeyboardError> {
+        if key > 127 {
+            return Err(KeyboardError::KeyOutOfRange(key));
+        }
+        self.base_key = key;
+        Ok(self)
+    }
+    /// The number of hexagons per row and the number of rows.
+    pub fn set_size(mut self, columns: u16, rows: u16) -> Result<HexBuilder, KeyboardError> {
+        if columns == 0 || rows == 0 || columns > 1000 || rows > 1000 {
+            return Err(KeyboardError::InvalidGridSize { columns, rows });
+        }
+        self.columns = columns;
+        self.rows = rows;
+        self.check_width()
+    }
+    /// Sets the desired keyboard width in pixels.
+    pub fn set_width(mut self, width: u16) -> Result<HexBuilder, KeyboardError> {
+        self.width = width;
+        self.check_width()
+    }
+    fn check_width(self) -> Result<HexBuilder, KeyboardError> {
+        // At least two pixels per half hexagon and the gap
+        let min_width = 2 * (2 * self.columns + 1) + 1;
+        if self.width < min_width {
+            return Err(KeyboardError::WidthTooSmall {
+                width: self.width,
+                min_width,
+                nr_of_keys: self.columns,
+            });
+        }
+        if self.height_for(self.width) > 65535 {
+            let max_width = (min_width..self.width)
+                .rev()
+                .find(|w| self.height_for(*w) <= 65535)
+                .unwrap_or(min_width);
+            return Err(KeyboardError::WidthTooBig {
+                width: self.width,
+                max_width,
+            });
+        }
+        Ok(self)
+    }
+    // Height of a hexagon, the vertical size of its slanted edges and the total height
+    fn vertical(&self, width: u16) -> (u32, u32, u32) {
+        // The hexagon is 2/sqrt(3) times higher than wide
+        let half_units = 2 * self.columns as u32 + 1;
+        let hex_height = (4 * width as u32 * 1000 / half_units / 1732).max(4);
+        let slant = hex_height / 4;
+        let total = (self.rows as u32 - 1) * (hex_height - slant) + hex_height;
+        (hex_height, slant, total)
+    }
+    fn height_for(&self, width: u16) -> u32 {
+        self.vertical(width).2
+    }
+    pub fn build(self) -> HexKeyboard {
+        let half_units = 2 * self.columns as u32 + 1;
+        let gap = ((2 * self.width as u32 / half_units) / 16).max(1) as u16;
+        let (hex_height, slant, height) = self.vertical(self.width);
+        let (hex_height, slant) = (hex_height as u16, slant as u16);
+
+        // The half hexagon boundaries are distributed pixel exact over the width.
+        // There is one gap at both ends.
+        let boundary = |i: u32| gap + ((self.width - gap) as u32 * i / half_units) as u16;
+        let mut hexagons = vec![];
+        for row in 0..self.rows {
+            let top = (self.rows - 1 - row) * (hex_height - slant);
+            for column in 0..self.columns {
+                // Axial coordinate along the rows
+                let q = column as i32 - (row / 2) as i32;
+                let key = self.base_key as i32
+                    + q * self.step_right as i32
+                    + row as i32 * self.step_up_right as i32;
+                if !(0..=127).contains(&key) {
+                    continue;
+                }
+                let a = 2 * column as u32 + (row % 2) as u32;
+                let left = boundary(a);
+                let right = boundary(a + 2) - gap;
+                let mid = (left + right) / 2;
+                hexagons.push(Hexagon {
+                    key: key as u8,
+                    column,
+                    row,
+                    points: [
+                        (mid, top),
+                        (right, top + slant),
+                        (right, top + hex_height - slant),
+                        (mid, top + hex_height),
+                        (left, top + hex_height - slant),
+                        (left, top + slant),
+                    ],
+                });
+            }
+        }
+
+        HexKeyboard {
+            width: self.width,
+            height: height as u16,
+            hexagons,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{HexBuilder, Hit, KeyboardError};
+
+    #[test]
+    fn test_wicki_hayden() {
+        let keyboard = HexBuilder::wicki_hayden()
+            .set_size(10, 6)
+            .unwrap()
+            .set_width(500)
+            .unwrap()
+            .build();
+        assert_eq!(keyboard.iter().count(), 60);
+        let at = |c, r| {
+            keyboard
+                .iter()
+                .find(|h| h.column == c && h.row == r)
+                .unwrap()
+        };
+        assert_eq!(at(0, 0).key, 48);
+        assert_eq!(at(1, 0).key, 50);
+        // Upper right and upper left neighbours
+        assert_eq!(at(0, 1).key, 55);
+        assert_eq!(at(1, 2).key, 48 + 2 * 7 + 2 - 2);
+        assert_eq!(at(1, 3).key, at(1, 2).key + 7);
+        // Pixel exact fit to the width with one gap at both ends
+        let left = keyboard.iter().map(|h| h.points[4].0).min().unwrap();
+        let right = keyboard.iter().map(|h| h.points[1].0).max().unwrap();
+        assert_eq!(500 - right, left);
+        assert!(keyboard.hexagons_of(62).count() >= 2);
+
+        // No pixel belongs to two hexagons and the center of each hexagon hits its key
+        for y in 0..keyboard.height {
+            for x in 0..keyboard.width {
+                assert!(keyboard.iter().filter(|h| h.contains(x, y)).count() <= 1);
+            }
+        }
+        for h in keyboard.iter() {
+            let x = (h.points[1].0 + h.points[4].0) / 2;
+            let y = (h.points[0].1 + h.points[3].1) / 2;
+            assert_eq!(keyboard.key_at(x, y), Hit::Key(h.key));
+        }
+        assert_eq!(keyboard.key_at(0, 0), Hit::Gap);
+        assert_eq!(keyboard.key_at(500, 0), Hit::Outside);
+    }
+    #[test]
+    fn test_harmonic_table() {
+        let keyboard = HexBuilder::harmonic_table()
+            .set_base_key(100)
+            .unwrap()
+            .build();
+        // Notes above 127 are left out
+        assert!(keyboard.iter().all(|h| h.key >= 100));
+        assert!(keyboard.iter().count() < 12 * 8);
+        for width in 60..700 {
+            let builder = HexBuilder::harmonic_table()
+                .set_base_key(24)
+                .unwrap()
+                .set_width(width);
+            if let Ok(builder) = builder {
+                let keyboard = builder.build();
+                let right = keyboard.iter().map(|h| h.points[1].0).max().unwrap();
+                let left = keyboard.iter().map(|h| h.points[4].0).min().unwrap();
+                assert_eq!(width - right, left);
+            }
+        }
+        assert!(HexBuilder::harmonic_table().set_width(20).is_err());
+        assert!(HexBuilder::harmonic_table().set_size(0, 2).is_err());
+        assert!(HexBuilder::harmonic_table().set_size(1, 1000).is_err());
+        let builder = HexBuilder::harmonic_table().set_size(1, 100).unwrap();
+        match builder.clone().set_width(1200) {
+            Err(KeyboardError::WidthTooBig { max_width, .. }) => {
+                assert!(builder.clone().set_width(max_width).is_ok());
+                assert!(builder.set_width(max_width + 1).is_err());
+            }
+            _ => panic!("height should exceed the limit"),
+        }
+    }
+}
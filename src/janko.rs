@@ -0,0 +1,275 @@
+//! Jankó keyboard with six rows of staggered keys of equal width.
+//!
+//! Every row is a whole tone scale. The keys of the notes C, D, E, F#, G# and A#
+//! are in the rows 0, 2 and 4 counted from the top, the other notes in the rows 1, 3 and 5.
+//! The rows are shifted against each other by half a key, so a note is at the
+//! same horizontal position in all three rows.
+//!
+//! The colors of the keys follow the traditional keyboard.
+use crate::{Hit, KeyLayout, KeyboardBuilder, KeyboardError, Rectangle};
+
+const NR_OF_ROWS: u16 = 6;
+const MAX_ROW_HEIGHT: u16 = 10000;
+
+/// A key of the Jankó keyboard.
+#[derive(Clone, Debug)]
+pub struct JankoKey {
+    /// The MIDI key code
+    pub key: u8,
+    /// One rectangle per row from top to bottom
+    pub rects: Vec<Rectangle>,
+}
+impl JankoKey {
+    /// White or black according to the traditional keyboard
+    pub fn is_white(&self) -> bool {
+        KeyboardBuilder::is_white(self.key)
+    }
+    /// Returns true, if the pixel at x/y is part of this key.
+    pub fn contains(&self, x: u16, y: u16) -> bool {
+        self.rects.iter().any(|r| r.contains(x, y))
+    }
+}
+
+/// The Jankó keyboard as returned by JankoBuilder::build().
+pub struct JankoKeyboard {
+    pub left_key: u8,
+    pub right_key: u8,
+    pub width: u16,
+    pub height: u16,
+    keys: Vec<JankoKey>,
+}
+impl JankoKeyboard {
+    /// The sequence is from low to high keys.
+    pub fn iter(&self) -> std::slice::Iter<'_, JankoKey> {
+        self.keys.iter()
+    }
+    /// Retrieve the key for a given MIDI key code.
+    pub fn key(&self, key: u8) -> Option<&JankoKey> {
+        if key < self.left_key {
+            return None;
+        }
+        self.keys.get((key - self.left_key) as usize)
+    }
+    /// All rectangles of the keys colored white.
+    /// There are no blind parts, so the parameter has no effect.
+    pub fn white_keys(&self, _blind_as_white: bool) -> Vec<Rectangle> {
+        self.rects_of(true)
+    }
+    /// All rectangles of the keys colored black.
+    pub fn black_keys(&self) -> Vec<Rectangle> {
+        self.rects_of(false)
+    }
+    /// Determine the key under the pixel at x/y like Keyboard2d::key_at().
+    pub fn key_at(&self, x: u16, y: u16) -> Hit {
+        if x >= self.width || y >= self.height {
+            return Hit::Outside;
+        }
+        self.keys
+            .iter()
+            .find(|k| k.contains(x, y))
+            .map(|k| Hit::Key(k.key))
+            .unwrap_or(Hit::Gap)
+    }
+    fn rects_of(&self, white: bool) -> Vec<Rectangle> {
+        self.keys
+            .iter()
+            .filter(|k| k.is_white() == white)
+            .flat_map(|k| k.rects.iter().cloned())
+            .collect()
+    }
+}
+impl KeyLayout for JankoKeyboard {
+    fn size(&self) -> (u16, u16) {
+        (self.width, self.height)
+    }
+    fn white_keys(&self, blind_as_white: bool) -> Vec<Rectangle> {
+        JankoKeyboard::white_keys(self, blind_as_white)
+    }
+    fn black_keys(&self) -> Vec<Rectangle> {
+        JankoKeyboard::black_keys(self)
+    }
+    fn key_at(&self, x: u16, y: u16) -> Hit {
+        JankoKeyboard::key_at(self, x, y)
+    }
+    fn key_rects(&self, key: u8) -> Vec<Rectangle> {
+        self.key(key).map(|k| k.rects.clone()).unwrap_or_default()
+    }
+}
+
+/// The builder for a Jankó keyboard.
+#[derive(Clone)]
+pub struct JankoBuilder {
+    left_key: u8,
+    right_key: u8,
+    width: u16,
+    row_height: Option<u16>,
+}
+impl Default for JankoBuilder {
+    fn default() -> Self {
+        JankoBuilder::new()
+    }
+}
+impl JankoBuilder {
+    pub fn new() -> JankoBuilder {
+        JankoBuilder {
+            left_key: 21,
+            right_key: 108,
+            width: 640,
+            row_height: None,
+        }
+    }
+    /// Set the lowest and highest key. Any key can be at the edges.
+    pub fn set_most_left_right_keys(
+        mut self,
+        left_key: u8,
+        right_key: u8,
+    ) -> Result<JankoBuilder, KeyboardError> {
+        if left_key > right_key {
+            Err(KeyboardError::KeysSwapped {
+                left: left_key,
+                right: right_key,
+            })
+        } else if right_key > 127 {
+            Err(KeyboardError::KeyOutOfRange(right_key))
+        } else {
+            self.left_key = left_key;
+            self.right_key = right_key;
+            self.check_width()
+        }
+    }
+    /// Sets the desired keyboard width in pixels.
+    pub fn set_width(mut self, width: u16) -> Result<JankoBuilder, KeyboardError> {
+        self.width = width;
+        self.check_width()
+    }
+    /// Sets the height of one row without the gap.
+    /// Default is the width of a key, which yields square keys.
+    pub fn set_row_height(mut self, row_height: u16) -> Result<JankoBuilder, KeyboardError> {
+        if row_height == 0 || row_height > MAX_ROW_HEIGHT {
+            return Err(KeyboardError::InvalidRowHeight(row_height));
+        }
+        self.row_height = Some(row_height);
+        Ok(self)
+    }
+    fn check_width(self) -> Result<JankoBuilder, KeyboardError> {
+        // Each key spans two units. At least two pixels per unit and the gap.
+        let nr_of_keys = (self.right_key - self.left_key) as u16 + 1;
+        let min_width = 2 * (nr_of_keys + 1) + 1;
+        if self.width < min_width {
+            Err(KeyboardError::WidthTooSmall {
+                width: self.width,
+                min_width,
+                nr_of_keys,
+            })
+        } else {
+            Ok(self)
+        }
+    }
+    pub fn build(self) -> JankoKeyboard {
+        let nr_of_keys = (self.right_key - self.left_key) as u32 + 1;
+        let nr_of_units = nr_of_keys + 1;
+        let pitch = (2 * self.width as u32 / nr_of_units) as u16;
+        let gap = (pitch / 16).clamp(1, 100);
+        let row_height = self
+            .row_height
+            .unwrap_or_else(|| (pitch - gap).min(MAX_ROW_HEIGHT));
+
+        // The unit boundaries are distributed pixel exact over the width
+        let unit = |i: u32| gap + ((self.width - gap) as u32 * i / nr_of_units) as u16;
+        let keys = (self.left_key..=self.right_key)
+            .map(|key| {
+                let i = (key - self.left_key) as u32;
+                let x = unit(i);
+                let width = unit(i + 2) - x - gap;
+                let first_row = (key % 2) as u16;
+                let rects = (0..NR_OF_ROWS / 2)
+                    .map(|r| Rectangle {
+                        x,
+                        y: gap + (first_row + 2 * r) * (row_height + gap),
+                        width,
+                        height: row_height,
+                    })
+                    .collect();
+                JankoKey { key, rects }
+            })
+            .collect();
+
+        JankoKeyboard {
+            left_key: self.left_key,
+            right_key: self.right_key,
+            width: self.width,
+            height: gap + NR_OF_ROWS * (row_height + gap),
+            keys,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Hit, JankoBuilder, KeyLayout, KeyboardBuilder};
+
+    #[test]
+    fn test_janko() {
+        let keyboard = JankoBuilder::new()
+            .set_most_left_right_keys(48, 72)
+            .unwrap()
+            .set_width(520)
+            .unwrap()
+            .build();
+        assert_eq!(keyboard.iter().count(), 25);
+        let c = keyboard.key(60).unwrap();
+        let cis = keyboard.key(61).unwrap();
+        let d = keyboard.key(62).unwrap();
+        assert_eq!(c.rects.len(), 3);
+        // Neighbouring notes are staggered by half a key
+        assert!(c.rects[0].y < cis.rects[0].y);
+        assert_eq!(d.rects[0].y, c.rects[0].y);
+        assert!(cis.rects[0].x > c.rects[0].x && cis.rects[0].x < d.rects[0].x);
+        // Whole tones are adjacent in a row
+        assert!(d.rects[0].x > c.rects[0].x + c.rects[0].width);
+        for k in keyboard.iter() {
+            assert!(k.rects.iter().all(|r| r.x == k.rects[0].x));
+            for r in k.rects.iter() {
+                assert_eq!(keyboard.key_at(r.x, r.y), Hit::Key(k.key));
+                assert_eq!(
+                    keyboard.key_at(r.x + r.width - 1, r.y + r.height - 1),
+                    Hit::Key(k.key)
+                );
+            }
+        }
+        // The last key ends one gap before the right edge
+        let last = keyboard.key(72).unwrap();
+        let gap = last.rects[0].x
+            - keyboard.key(70).unwrap().rects[0].width
+            - keyboard.key(70).unwrap().rects[0].x;
+        assert_eq!(last.rects[0].x + last.rects[0].width + gap, keyboard.width);
+        assert_eq!(keyboard.key_at(0, 0), Hit::Gap);
+        assert_eq!(keyboard.key_at(keyboard.width, 0), Hit::Outside);
+        assert_eq!(keyboard.white_keys(false).len(), 3 * 15);
+        assert_eq!(keyboard.black_keys().len(), 3 * 10);
+
+        let keyboard = JankoBuilder::new().set_row_height(7).unwrap().build();
+        assert_eq!(keyboard.key(21).unwrap().rects[0].height, 7);
+        assert!(JankoBuilder::new().set_width(100).is_err());
+        assert!(JankoBuilder::new().set_row_height(0).is_err());
+    }
+    #[test]
+    fn test_key_layout() {
+        // Same code for both layouts
+        fn pressed_area(layout: &dyn KeyLayout, key: u8) -> u32 {
+            layout
+                .key_rects(key)
+                .iter()
+                .map(|r| r.width as u32 * r.height as u32)
+                .sum()
+        }
+        let keyboard = KeyboardBuilder::new().build2d();
+        let janko = JankoBuilder::new().build();
+        for layout in [&keyboard as &dyn KeyLayout, &janko] {
+            assert!(pressed_area(layout, 60) > 0);
+            assert_eq!(pressed_area(layout, 20), 0);
+            let (width, height) = layout.size();
+            assert_eq!(layout.key_at(width, height), Hit::Outside);
+        }
+    }
+}
@@ -0,0 +1,241 @@
+//! The 3d keyboard built from the same key dimensions as the 2d keyboard.
+//!
+//! The footprint of the keys is taken from the fractional 2d geometry in millimetres.
+//! The keys are then extruded upwards using KeyDimensions3d.
+use crate::{ElementF32, KeyboardBuilder, Orientation, RectangleF32};
+
+/// An axis aligned box in millimetres.
+///
+/// x is along the key axis from low to high keys, y from the back to the front
+/// of the keys and z upwards from the key bed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Box3d {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub width: f32,
+    pub length: f32,
+    pub height: f32,
+}
+impl Box3d {
+    fn extrude(r: &RectangleF32, unit_mm: f32, z: f32, height: f32) -> Box3d {
+        Box3d {
+            x: r.x * unit_mm,
+            y: r.y * unit_mm,
+            z,
+            width: r.width * unit_mm,
+            length: r.height * unit_mm,
+            height,
+        }
+    }
+    /// The eight corners of the box. The first four are at the bottom.
+    pub fn corners(&self) -> [[f32; 3]; 8] {
+        let (x0, y0, z0) = (self.x, self.y, self.z);
+        let (x1, y1, z1) = (x0 + self.width, y0 + self.length, z0 + self.height);
+        [
+            [x0, y0, z0],
+            [x1, y0, z0],
+            [x1, y1, z0],
+            [x0, y1, z0],
+            [x0, y0, z1],
+            [x1, y0, z1],
+            [x1, y1, z1],
+            [x0, y1, z1],
+        ]
+    }
+}
+
+/// A key as solid consisting of non-overlapping boxes.
+#[derive(Clone, Debug)]
+pub struct Key3d {
+    /// The MIDI key code
+    pub key: u8,
+    pub parts: Vec<Box3d>,
+    white: bool,
+}
+impl Key3d {
+    pub fn is_white(&self) -> bool {
+        self.white
+    }
+}
+
+/// The 3d keyboard as returned by KeyboardBuilder::build3d().
+///
+/// All measures are in millimetres.
+pub struct Keyboard3d {
+    pub left_key: u8,
+    pub right_key: u8,
+    pub width: f32,
+    pub length: f32,
+    pub height: f32,
+    /// All keys pivot around one axis parallel to x. This is the y position of the axis,
+    /// which is negative, because the pivot is behind the visible part of the keys.
+    pub pivot_y: f32,
+    /// The z position of the pivot axis
+    pub pivot_z: f32,
+    keys: Vec<Key3d>,
+    blinds: Vec<Box3d>,
+}
+impl Keyboard3d {
+    /// The sequence is from low to high keys like Keyboard2d::iter()
+    pub fn iter(&self) -> std::slice::Iter<'_, Key3d> {
+        self.keys.iter()
+    }
+    /// Retrieve the key for a given MIDI key code.
+    pub fn key(&self, key: u8) -> Option<&Key3d> {
+        if key < self.left_key {
+            return None;
+        }
+        self.keys.get((key - self.left_key) as usize)
+    }
+    /// The visible parts of the white keys outside of the key range,
+    /// if the keyboard starts or ends with a black key.
+    pub fn blinds(&self) -> &[Box3d] {
+        &self.blinds
+    }
+}
+
+impl KeyboardBuilder {
+    /// Build a 3d keyboard for the key range.
+    ///
+    /// The width of the keyboard is given by the key dimensions and not by set_width().
+    /// Orientation, mirroring and dot ratio of the 2d keyboard are not applied.
+    pub fn build3d(mut self) -> Keyboard3d {
+        let dims_3d = self.dims_3d.clone().unwrap_or_default();
+        let mm = |v: u32| v as f32 / 100.0;
+        let thickness = mm(dims_3d.white_key_thickness_10um);
+        let raise = mm(dims_3d.black_key_raise_10um);
+        let lip_height = mm(dims_3d.front_lip_height_10um);
+
+        self.orientation = Orientation::Horizontal;
+        self.mirror_horizontal = false;
+        self.mirror_vertical = false;
+        self.dot_ratio_1024 = 1024;
+        self.stretch_1024 = 1024;
+        let keyboard = self.build2d_f32();
+        let unit_mm = keyboard.unit_mm;
+        let lip_depth = mm(dims_3d.front_lip_depth_10um).min(keyboard.height * unit_mm);
+
+        let mut keys = vec![];
+        let mut front: f32 = 0.0;
+        for el in keyboard.iter() {
+            let key = match el {
+                ElementF32::WhiteKey {
+                    wide, small, blind, ..
+                } => {
+                    // The wide part carries the overhanging lip at the front
+                    let mut body = Box3d::extrude(wide, unit_mm, 0.0, thickness);
+                    body.length -= lip_depth;
+                    let lip = Box3d {
+                        y: body.y + body.length,
+                        z: thickness - lip_height,
+                        length: lip_depth,
+                        height: lip_height,
+                        ..body.clone()
+                    };
+                    front = front.max(lip.y + lip.length);
+                    let mut parts = vec![body, lip];
+                    parts.extend(
+                        Some(small)
+                            .into_iter()
+                            .chain(blind.iter())
+                            .map(|r| Box3d::extrude(r, unit_mm, 0.0, thickness)),
+                    );
+                    parts.retain(|b| b.width > 0.0 && b.length > 0.0);
+                    Key3d {
+                        key: el.key(),
+                        parts,
+                        white: true,
+                    }
+                }
+                ElementF32::BlackKey(r, key) => Key3d {
+                    key: *key,
+                    parts: vec![Box3d::extrude(r, unit_mm, 0.0, thickness + raise)],
+                    white: false,
+                },
+            };
+            keys.push(key);
+        }
+        if front == 0.0 {
+            // Only black keys
+            front = keyboard.height * unit_mm;
+        }
+        let blinds = keyboard
+            .blinds
+            .iter()
+            .map(|r| Box3d::extrude(r, unit_mm, 0.0, thickness))
+            .collect();
+
+        Keyboard3d {
+            left_key: keyboard.left_key,
+            right_key: keyboard.right_key,
+            width: keyboard.width * unit_mm,
+            length: keyboard.height * unit_mm,
+            height: thickness + raise,
+            pivot_y: front - mm(dims_3d.pivot_distance_10um),
+            pivot_z: 0.0,
+            keys,
+            blinds,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{KeyDimensions3d, KeyboardBuilder};
+
+    #[test]
+    fn test_build3d() {
+        let keyboard = KeyboardBuilder::new().standard_piano(88).unwrap().build3d();
+        assert_eq!(keyboard.iter().count(), 88);
+        // 52 white keys of 22.15mm plus 53 gaps
+        let gap = 126.27 - 80.0 - 45.0;
+        assert!((keyboard.width - (52.0 * 22.15 + 53.0 * gap)).abs() < 0.1);
+        assert!((keyboard.height - 34.0).abs() < 0.001);
+        assert!(keyboard.pivot_y < -100.0);
+        for key in keyboard.iter() {
+            let top = key.parts.iter().map(|b| b.z + b.height).fold(0.0, f32::max);
+            if key.is_white() {
+                assert!((top - 22.0).abs() < 0.001);
+                // body, lip and small part
+                assert_eq!(
+                    key.parts.len(),
+                    if key.key == 21 || key.key == 108 {
+                        4
+                    } else {
+                        3
+                    }
+                );
+            } else {
+                assert!((top - 34.0).abs() < 0.001);
+            }
+        }
+        let c4 = keyboard.key(60).unwrap();
+        let lip = &c4.parts[1];
+        assert!((lip.length - 2.0).abs() < 0.001);
+        assert!((lip.z - 19.0).abs() < 0.001);
+        assert!((lip.y + lip.length - keyboard.length + gap).abs() < 0.01);
+    }
+    #[test]
+    fn test_dimensions_3d() {
+        let builder = KeyboardBuilder::new().set_key_dimensions_3d(KeyDimensions3d {
+            black_key_raise_10um: 10_00,
+            ..KeyDimensions3d::default()
+        });
+        let keyboard = builder.unwrap().build3d();
+        assert!((keyboard.height - 32.0).abs() < 0.001);
+        assert!(KeyboardBuilder::new()
+            .set_key_dimensions_3d(KeyDimensions3d {
+                pivot_distance_10um: 50_00,
+                ..KeyDimensions3d::default()
+            })
+            .is_err());
+        // A black key at the edge leaves a visible part of the neighbouring white key
+        let keyboard = KeyboardBuilder::new()
+            .set_most_left_right_keys(61, 72)
+            .unwrap()
+            .build3d();
+        assert!(!keyboard.blinds().is_empty());
+        assert!(!keyboard.key(61).unwrap().is_white());
+    }
+}
@@ -0,0 +1,206 @@
+//! Note lanes for a piano roll next to the keyboard.
+//!
+//! The lanes are derived from the back of the keys, where white and black keys
+//! are side by side. A black key gets the span of the black key and a white key
+//! the span of its small part. The gaps between them are split in the middle,
+//! so the lanes cover the full length of the keyboard without gaps or overlaps.
+//! Every lane is at least one pixel wide.
+use crate::{Element, Keyboard2d, Rectangle};
+
+/// The span of the lane for one key along the key axis.
+///
+/// This is x for a horizontal keyboard and y for a vertical keyboard.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Lane {
+    /// The MIDI key code
+    pub key: u8,
+    pub start: u16,
+    pub width: u16,
+}
+
+impl Keyboard2d {
+    /// The lanes of all keys from low to high keys.
+    ///
+    /// For a mirrored or VerticalLowAtBottom keyboard the lanes of the high keys
+    /// have the lower coordinates.
+    pub fn lanes(&self) -> Vec<Lane> {
+        let canonical = |r: &Rectangle| self.transform.inverse_rect(r);
+        // The back part of a key in the canonical layout
+        let back = |el: &Element| match el {
+            Element::WhiteKey { small, .. } => canonical(small),
+            Element::BlackKey(r, _) => canonical(r),
+        };
+        let length = canonical(&Rectangle {
+            x: 0,
+            y: 0,
+            width: self.width,
+            height: self.height,
+        });
+
+        let mut boundaries = vec![0];
+        for pair in self.elements.windows(2) {
+            let left = back(&pair[0]);
+            let right = back(&pair[1]);
+            // Wide keyboards would overflow u16
+            let middle = (left.x as u32 + left.width as u32 + right.x as u32) / 2;
+            boundaries.push(middle as u16);
+        }
+        boundaries.push(length.width);
+        // At very small widths the back part of a white key may be empty. Every lane
+        // keeps at least one pixel, even if it overlaps the back part of a neighbour then.
+        let n = boundaries.len() - 1;
+        for i in 1..n {
+            boundaries[i] = boundaries[i].max(boundaries[i - 1] + 1);
+        }
+        for i in (1..n).rev() {
+            boundaries[i] = boundaries[i].min(boundaries[i + 1] - 1);
+        }
+
+        self.elements
+            .iter()
+            .zip(boundaries.windows(2))
+            .map(|(el, b)| {
+                let r = self.transform.rect(&Rectangle {
+                    x: b[0],
+                    y: 0,
+                    width: b[1] - b[0],
+                    height: length.height,
+                });
+                if self.transform.is_vertical() {
+                    Lane {
+                        key: el.key(),
+                        start: r.y,
+                        width: r.height,
+                    }
+                } else {
+                    Lane {
+                        key: el.key(),
+                        start: r.x,
+                        width: r.width,
+                    }
+                }
+            })
+            .collect()
+    }
+    /// The lane of one key. Returns None, if the key is not part of the keyboard.
+    pub fn lane(&self, key: u8) -> Option<Lane> {
+        self.lanes().into_iter().find(|l| l.key == key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Element, KeyPattern, Keyboard2d, KeyboardBuilder, KeyboardError, Orientation};
+
+    // The lanes cover the keyboard without gaps or overlaps
+    fn check_coverage(keyboard: &Keyboard2d) {
+        let vertical = keyboard.orientation() != Orientation::Horizontal;
+        let length = if vertical {
+            keyboard.height
+        } else {
+            keyboard.width
+        };
+        let mut lanes = keyboard.lanes();
+        assert_eq!(lanes.len(), keyboard.iter().count());
+        lanes.sort_by_key(|l| l.start);
+        assert_eq!(lanes[0].start, 0);
+        for pair in lanes.windows(2) {
+            assert_eq!(pair[0].start + pair[0].width, pair[1].start);
+        }
+        let last = lanes.last().unwrap();
+        assert_eq!(last.start + last.width, length);
+        assert!(lanes.iter().all(|l| l.width > 0));
+    }
+
+    fn check_lanes(keyboard: &Keyboard2d) {
+        check_coverage(keyboard);
+        // The back part of every key is within its lane
+        let vertical = keyboard.orientation() != Orientation::Horizontal;
+        for el in keyboard.iter() {
+            let lane = keyboard.lane(el.key()).unwrap();
+            let r = match el {
+                Element::WhiteKey { small, .. } => small,
+                Element::BlackKey(r, _) => r,
+            };
+            let (start, width) = if vertical {
+                (r.y, r.height)
+            } else {
+                (r.x, r.width)
+            };
+            assert!(start >= lane.start && start + width <= lane.start + lane.width);
+        }
+    }
+
+    #[test]
+    fn test_lanes() {
+        for orientation in [
+            Orientation::Horizontal,
+            Orientation::VerticalLowAtBottom,
+            Orientation::VerticalLowAtTop,
+        ] {
+            for mirror in [false, true] {
+                for (left, right) in [(21, 108), (48, 72), (49, 70), (61, 61)] {
+                    for width in [200, 531, 1000] {
+                        let builder = KeyboardBuilder::new()
+                            .set_most_left_right_keys(left, right)
+                            .unwrap()
+                            .set_width(width);
+                        let keyboard = match builder {
+                            Ok(builder) => builder
+                                .set_orientation(orientation)
+                                .set_mirror(mirror, mirror)
+                                .build2d(),
+                            Err(_) => continue,
+                        };
+                        check_lanes(&keyboard);
+                    }
+                }
+            }
+        }
+        let keyboard = KeyboardBuilder::new().build2d();
+        assert!(keyboard.lane(20).is_none());
+        let keyboard = KeyboardBuilder::new()
+            .set_key_pattern(KeyPattern::edo31())
+            .unwrap()
+            .set_width(1000)
+            .unwrap()
+            .try_build2d()
+            .unwrap();
+        check_lanes(&keyboard);
+    }
+    #[test]
+    fn test_wide_lanes() {
+        for orientation in [Orientation::Horizontal, Orientation::VerticalLowAtTop] {
+            let builder = KeyboardBuilder::new().set_orientation(orientation);
+            let max_width = match builder.clone().set_width(u16::MAX) {
+                Err(KeyboardError::WidthTooBig { max_width, .. }) => max_width,
+                _ => unreachable!(),
+            };
+            assert!(max_width > 60000);
+            for width in [40000, max_width] {
+                check_lanes(&builder.clone().set_width(width).unwrap().build2d());
+            }
+        }
+    }
+    #[test]
+    fn test_narrow_lanes() {
+        // Some white keys have no back part at these widths
+        for (left, right) in [(42, 59), (21, 108), (60, 60)] {
+            let builder = KeyboardBuilder::new()
+                .set_most_left_right_keys(left, right)
+                .unwrap();
+            for width in 3 * (right - left) as u16..400 {
+                if let Ok(builder) = builder.clone().set_width(width) {
+                    check_coverage(&builder.build2d());
+                }
+            }
+        }
+        let keyboard = KeyboardBuilder::new()
+            .set_most_left_right_keys(42, 59)
+            .unwrap()
+            .set_width(68)
+            .unwrap()
+            .build2d();
+        assert_eq!(keyboard.lane(59).map(|l| l.width), Some(1));
+    }
+}
@@ -0,0 +1,40 @@
+//! Common interface of the keyboard layouts.
+//!
+//! A renderer or hit test written against KeyLayout works for the traditional
+//! Keyboard2d and for the alternative layouts like JankoKeyboard.
+use crate::{Hit, Keyboard2d, Rectangle};
+
+/// The keyed rectangles of a keyboard layout.
+pub trait KeyLayout {
+    /// Width and height in pixels
+    fn size(&self) -> (u16, u16);
+    /// All rectangles of the white keys. See Keyboard2d::white_keys().
+    fn white_keys(&self, blind_as_white: bool) -> Vec<Rectangle>;
+    /// All rectangles of the black keys
+    fn black_keys(&self) -> Vec<Rectangle>;
+    /// Determine the key under the pixel at x/y.
+    fn key_at(&self, x: u16, y: u16) -> Hit;
+    /// The rectangles of one key, e.g. to highlight a pressed key.
+    /// Empty, if the key is not part of the keyboard.
+    fn key_rects(&self, key: u8) -> Vec<Rectangle>;
+}
+
+impl KeyLayout for Keyboard2d {
+    fn size(&self) -> (u16, u16) {
+        (self.width, self.height)
+    }
+    fn white_keys(&self, blind_as_white: bool) -> Vec<Rectangle> {
+        Keyboard2d::white_keys(self, blind_as_white)
+    }
+    fn black_keys(&self) -> Vec<Rectangle> {
+        Keyboard2d::black_keys(self)
+    }
+    fn key_at(&self, x: u16, y: u16) -> Hit {
+        Keyboard2d::key_at(self, x, y)
+    }
+    fn key_rects(&self, key: u8) -> Vec<Rectangle> {
+        self.element(key)
+            .map(|el| el.rects().into_iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
@@ -23,34 +23,112 @@
 //! any gaps, white or black keys may need to be modified by up to one pixel.
 //! Those changes may or may not be visible. If no adjustments have been made for
 //! a given width and key range is reported by the function is_perfect()
+//! and the applied adjustments are listed by the function report().
 //!
 //! If the enlargement of various elements does not succeed, then as last resort
 //! technique the outter gaps are enlarged.
 //!
 //! The gap between white and black keys can be removed by an option of the KeyboardBuilder.
 //!
+//! Other octave divisions like 19-EDO or 31-EDO are laid out with a KeyPattern
+//! given to KeyboardBuilder::set_key_pattern().
+//!
+//! For simple raster output Keyboard2d::paint_rgba() fills an RGBA pixel buffer.
+//!
 //! The interface is prepared to be compatible for an extension towards a 3d keyboard.
 //! That's why the returned keyboard is called Keyboard2D and the related build function
 //! is called build2d().
+//!
+//! With the optional feature `serde` the Keyboard2d and its elements can be serialized
+//! and deserialized, e.g. to cache computed layouts.
+//! The feature `svg` adds the vector output Keyboard2d::to_svg().
+//! The feature `terminal` adds the output as block character cells by Keyboard2d::to_cells()
+//! and the feature `ratatui` a widget to render them with ratatui.
+//! The crate builds with Rust 1.62, but the feature `ratatui` needs Rust 1.74 or newer.
 
 mod base;
+mod dimensions;
+mod error;
+mod fit;
+mod geometry;
+mod hex;
+mod janko;
+mod keyboard3d;
+mod lanes;
+mod layout;
+mod manuals;
+mod microtonal;
+mod outline;
+mod raster;
+mod report;
+mod search;
+#[cfg(feature = "serde")]
+mod serialize;
+#[cfg(feature = "svg")]
+mod svg;
+#[cfg(feature = "terminal")]
+mod terminal;
 mod top;
+mod transform;
 use crate::base::Base;
+pub use crate::dimensions::{KeyDimensions, KeyDimensions3d};
+pub use crate::error::{DimensionError, KeyboardError};
+pub use crate::fit::{Fit, FitStrategy};
+pub use crate::geometry::{ElementF32, Keyboard2dF32, RectangleF32};
+pub use crate::hex::{HexBuilder, HexKeyboard, Hexagon};
+pub use crate::janko::{JankoBuilder, JankoKey, JankoKeyboard};
+pub use crate::keyboard3d::{Box3d, Key3d, Keyboard3d};
+pub use crate::lanes::Lane;
+pub use crate::layout::KeyLayout;
+pub use crate::manuals::{Manual, Manuals, ManualsBuilder};
+pub use crate::microtonal::KeyPattern;
+pub use crate::raster::RgbaStyle;
+pub use crate::report::{Adjustment, AdjustmentKind, LayoutReport, Target};
+#[cfg(feature = "svg")]
+pub use crate::svg::SvgStyle;
+#[cfg(feature = "ratatui")]
+pub use crate::terminal::KeyboardWidget;
+#[cfg(feature = "terminal")]
+pub use crate::terminal::{Blocks, Cell, TerminalStyle};
 use crate::top::{Top, TopResultElement};
+pub use crate::transform::Orientation;
+use crate::transform::Transform;
 
 /// This is just another rectangle definition.
 ///
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Rectangle {
     pub x: u16,
     pub y: u16,
     pub width: u16,
     pub height: u16,
 }
+impl Rectangle {
+    /// Returns true, if the pixel at x/y is part of the rectangle.
+    pub fn contains(&self, x: u16, y: u16) -> bool {
+        x >= self.x && x - self.x < self.width && y >= self.y && y - self.y < self.height
+    }
+}
+
+/// The result of a hit test on a keyboard for a given pixel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Hit {
+    /// The pixel belongs to the key with this MIDI key code.
+    Key(u8),
+    /// The pixel is within the keyboard, but not on any key.
+    Gap,
+    /// The pixel is outside of the keyboard.
+    Outside,
+}
 
 /// The elements provided by a Keyboard are white keys, black keys and the full keyboard - defined
 /// by this enum.
-#[derive(Debug)]
+///
+/// Every element carries the MIDI key code of the key it represents.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Element {
     /// A white key consists of up to three rectangles:
     ///     The wide part of the key.
@@ -60,131 +138,365 @@ pub enum Element {
         wide: Rectangle,
         small: Rectangle,
         blind: Option<Rectangle>,
+        key: u8,
     },
-    /// A black key consists of only one rectangle
-    BlackKey(Rectangle),
+    /// A black key consists of only one rectangle and its MIDI key code
+    BlackKey(Rectangle, u8),
+}
+impl Element {
+    /// The MIDI key code of this element.
+    pub fn key(&self) -> u8 {
+        match self {
+            Element::WhiteKey { key, .. } => *key,
+            Element::BlackKey(_, key) => *key,
+        }
+    }
+    /// The pitch class of this element with 0 for C up to 11 for B.
+    /// This is only valid for the twelve tone key pattern.
+    #[deprecated(
+        since = "0.3.0",
+        note = "use Keyboard2d::key_pattern().pitch_class(key), which supports all key patterns"
+    )]
+    pub fn pitch_class(&self) -> u8 {
+        self.key() % 12
+    }
+    /// The octave of this element following the MIDI convention,
+    /// that key code 0 is C_-1 and key code 60 is C_4.
+    /// This is only valid for the twelve tone key pattern.
+    #[deprecated(
+        since = "0.3.0",
+        note = "use Keyboard2d::key_pattern().octave(key), which supports all key patterns"
+    )]
+    pub fn octave(&self) -> i8 {
+        (self.key() / 12) as i8 - 1
+    }
+    pub fn is_white(&self) -> bool {
+        match self {
+            Element::WhiteKey { .. } => true,
+            Element::BlackKey(..) => false,
+        }
+    }
+    /// Returns true, if the pixel at x/y is part of this key.
+    /// The blind part of a white key is considered as part of the key.
+    pub fn contains(&self, x: u16, y: u16) -> bool {
+        match self {
+            Element::WhiteKey {
+                wide, small, blind, ..
+            } => {
+                wide.contains(x, y)
+                    || small.contains(x, y)
+                    || blind.as_ref().map(|b| b.contains(x, y)).unwrap_or(false)
+            }
+            Element::BlackKey(r, _) => r.contains(x, y),
+        }
+    }
+    fn rects(&self) -> Vec<&Rectangle> {
+        match self {
+            Element::WhiteKey {
+                wide, small, blind, ..
+            } => {
+                let mut rects = vec![wide, small];
+                rects.extend(blind.iter());
+                rects
+            }
+            Element::BlackKey(r, _) => vec![r],
+        }
+    }
+    // Horizontal extent of the element in the canonical layout
+    fn start(&self) -> u16 {
+        self.rects().iter().map(|r| r.x).min().unwrap_or(0)
+    }
+    fn end(&self) -> u16 {
+        self.rects()
+            .iter()
+            .map(|r| r.x + r.width)
+            .max()
+            .unwrap_or(0)
+    }
+    fn map_rects(self, f: impl Fn(&Rectangle) -> Rectangle) -> Element {
+        match self {
+            Element::WhiteKey {
+                wide,
+                small,
+                blind,
+                key,
+            } => Element::WhiteKey {
+                wide: f(&wide),
+                small: f(&small),
+                blind: blind.as_ref().map(&f),
+                key,
+            },
+            Element::BlackKey(r, key) => Element::BlackKey(f(&r), key),
+        }
+    }
 }
 
 /// The returned 2d Keyboard with all calculated elements.
 pub struct Keyboard2d {
+    /// The most left key, which is a black key for a keyboard starting with a black key.
     pub left_white_key: u8,
+    /// The most right key, which is a black key for a keyboard ending with a black key.
     pub right_white_key: u8,
     pub width: u16,
     pub height: u16,
-    perfect: bool,
+    report: LayoutReport,
     elements: Vec<Element>,
+    // Visible parts of white keys outside of the key range
+    blinds: Vec<Rectangle>,
+    transform: Transform,
+    fit: Option<Fit>,
+    pattern: KeyPattern,
 }
 impl Keyboard2d {
     /// This function is the preferred way to iterate through all elements.
-    /// The sequence is from left to right alternating keys in order:
+    /// The sequence is from low to high keys (left to right for a horizontal keyboard) in order:
     /// white,black,white,....,black,white
     ///
-    pub fn iter(&self) -> std::slice::Iter<Element> {
+    pub fn iter(&self) -> std::slice::Iter<'_, Element> {
         self.elements.iter()
     }
+    /// Retrieve the element for a given MIDI key code.
+    /// Returns None, if the key is not part of the keyboard.
+    pub fn element(&self, key: u8) -> Option<&Element> {
+        if key < self.left_white_key {
+            return None;
+        }
+        self.elements.get((key - self.left_white_key) as usize)
+    }
     /// This function allows to retrieve all white key rectangles - with or without blind.
+    /// For a keyboard starting or ending with a black key, the blind includes the partially
+    /// visible white key next to it.
     pub fn white_keys(&self, blind_as_white: bool) -> Vec<Rectangle> {
         let mut rects = vec![];
         for opt_element in self.elements.iter() {
-            match opt_element {
-                Element::WhiteKey {
-                    wide: r1,
-                    small: r2,
-                    blind: opt_blind,
-                } => {
-                    rects.push(r1.clone());
-                    rects.push(r2.clone());
-                    if blind_as_white {
-                        if let Some(blind) = opt_blind {
-                            rects.push(blind.clone());
-                        }
+            if let Element::WhiteKey {
+                wide: r1,
+                small: r2,
+                blind: opt_blind,
+                ..
+            } = opt_element
+            {
+                rects.push(r1.clone());
+                rects.push(r2.clone());
+                if blind_as_white {
+                    if let Some(blind) = opt_blind {
+                        rects.push(blind.clone());
                     }
                 }
-                _ => (),
             }
         }
+        if blind_as_white {
+            rects.extend(self.blinds.iter().cloned());
+        }
         rects
     }
     /// This function allows to retrieve all black key rectangles.
     pub fn black_keys(&self) -> Vec<Rectangle> {
         let mut rects = vec![];
         for opt_element in self.elements.iter() {
-            match opt_element {
-                Element::BlackKey(r) => {
-                    rects.push(r.clone());
-                }
-                _ => (),
+            if let Element::BlackKey(r, _) = opt_element {
+                rects.push(r.clone());
             }
         }
         rects
     }
+    /// Determine the key under the pixel at x/y.
+    ///
+    /// The elements are grouped into a white key and the black keys following it.
+    /// These groups are sorted from low to high keys, so a binary search yields the
+    /// last group starting at or before the pixel along the key axis.
+    /// The pixel can only belong to this group or to the black keys of the group before,
+    /// because those black keys may extend over the wide part of the next white key.
+    pub fn key_at(&self, x: u16, y: u16) -> Hit {
+        if x >= self.width || y >= self.height {
+            return Hit::Outside;
+        }
+        let pos = self
+            .transform
+            .inverse_rect(&Rectangle {
+                x,
+                y,
+                width: 1,
+                height: 1,
+            })
+            .x;
+        // Black keys before the first white key belong to a group starting at 0
+        let group_start = |i: usize| {
+            self.elements[..=i]
+                .iter()
+                .rev()
+                .find(|el| el.is_white())
+                .map(|el| self.start_of(el))
+                .unwrap_or(0)
+        };
+        let (mut lo, mut hi) = (0, self.elements.len());
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            if group_start(mid) <= pos {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        let mut whites = 0;
+        for el in self.elements[..lo].iter().rev() {
+            if el.contains(x, y) {
+                return Hit::Key(el.key());
+            }
+            if el.is_white() {
+                whites += 1;
+                if whites == 2 {
+                    break;
+                }
+            }
+        }
+        Hit::Gap
+    }
+    /// The orientation of the keyboard as requested from the builder.
+    pub fn orientation(&self) -> Orientation {
+        self.transform.orientation()
+    }
+    /// Returns if the keyboard is mirrored horizontally and vertically.
+    pub fn mirror(&self) -> (bool, bool) {
+        self.transform.mirror()
+    }
+    // Start position of an element along the key axis in the canonical layout.
+    // All rectangles of a white key start at or after the wide part.
+    fn start_of(&self, el: &Element) -> u16 {
+        match el {
+            Element::WhiteKey { wide, .. } => self.transform.inverse_rect(wide).x,
+            Element::BlackKey(r, _) => self.transform.inverse_rect(r).x,
+        }
+    }
+    /// The result of KeyboardBuilder::fit_into(), if used.
+    pub fn fit(&self) -> Option<Fit> {
+        self.fit
+    }
+    /// The white/black pattern of the keys as set by KeyboardBuilder::set_key_pattern().
+    pub fn key_pattern(&self) -> &KeyPattern {
+        &self.pattern
+    }
     /// Is true for a keyboard without compromises on width/gaps.
     /// For example: cargo run --example make_png -- --width 811
+    /// Perfect widths can be searched by KeyboardBuilder::nearest_perfect_width().
+    /// The applied compromises are listed by report().
     pub fn is_perfect(&self) -> bool {
-        self.perfect
+        self.report.is_perfect()
+    }
+}
+
+// The sizes of a layout, which are derived from the width before the keys are laid out.
+struct Vertical {
+    key_gap: u32,
+    white_key_wide_width: u32,
+    key_gap_v: u32,
+    black_gap_v: u32,
+    black_key_height: u32,
+    white_key_wide_height: u32,
+}
+impl Vertical {
+    fn height(&self) -> u32 {
+        2 * self.key_gap_v + self.black_gap_v + self.black_key_height + self.white_key_wide_height
+    }
+    // The widths of the top part of an octave (f..b) are summed up in u16 by Top,
+    // which includes four white keys and three gaps.
+    fn fits(&self) -> bool {
+        self.height() <= 65535
+            && 4 * (self.white_key_wide_width + 2) + 3 * (self.key_gap + 1) <= 65535
     }
 }
 
 /// The central builder to create a keyboard.
+#[derive(Clone)]
 pub struct KeyboardBuilder {
-    left_white_key: u8,
-    right_white_key: u8,
+    left_key: u8,
+    right_key: u8,
     width: u16,
     dot_ratio_1024: u16, // dot height/dot width
-
-    white_key_wide_width_10um: u32,
-    //white_key_small_width_cde_10um: u32,
-    white_key_small_width_fb_10um: u32,
-    white_key_small_width_ga_10um: u32,
-
-    black_key_width_10um: u32,
-    black_key_height_10um: u32,
+    dims: KeyDimensions,
+    dims_3d: Option<KeyDimensions3d>,
     need_black_gap: bool,
-
-    white_key_height_10um: u32,
-    white_key_wide_height_10um: u32,
+    orientation: Orientation,
+    mirror_horizontal: bool,
+    mirror_vertical: bool,
+    stretch_1024: u16, // key length/natural key length
+    fit: Option<Fit>,
+    pattern: KeyPattern,
+    key_range_set: bool, // the key range is kept by set_key_pattern()
+}
+impl Default for KeyboardBuilder {
+    fn default() -> Self {
+        KeyboardBuilder::new()
+    }
 }
 impl KeyboardBuilder {
     pub fn new() -> KeyboardBuilder {
         KeyboardBuilder {
-            left_white_key: 21,
-            right_white_key: 108,
+            left_key: 21,
+            right_key: 108,
             width: 640,
             dot_ratio_1024: 1024,
-
-            // http://www.rwgiangiulio.com/construction/manual/layout.jpg
-            // below measures are in 10 µm
-            white_key_wide_width_10um: 22_15,
-            // following not needed, because assumption is equally spaced
-            //white_key_small_width_cde_10um: 13_97,
-            white_key_small_width_fb_10um: 12_83,
-            white_key_small_width_ga_10um: 13_08,
-
-            black_key_width_10um: 11_00,
-            black_key_height_10um: 80_00,
+            dims: KeyDimensions::default(),
+            dims_3d: None,
             need_black_gap: true,
-
-            white_key_height_10um: 126_27,
-            white_key_wide_height_10um: 45_00,
-        }
-    }
-    fn check_width(self) -> Result<KeyboardBuilder, String> {
-        let nr_of_keys = (self.right_white_key - self.left_white_key) as u16;
-        if self.width > 65535 - 127 {
-            Err(format!("Keyboard width {} too big", self.width))
-        } else if self.width < nr_of_keys * 3 {
-            Err(format!(
-                "Keyboard width too small {} < {} for {} keys",
-                self.width,
-                nr_of_keys * 3,
-                nr_of_keys
-            ))
+            orientation: Orientation::Horizontal,
+            mirror_horizontal: false,
+            mirror_vertical: false,
+            stretch_1024: 1024,
+            fit: None,
+            pattern: KeyPattern::twelve_tone(),
+            key_range_set: false,
+        }
+    }
+    fn check_width(self) -> Result<KeyboardBuilder, KeyboardError> {
+        let nr_of_keys = (self.right_key - self.left_key) as u16 + 1;
+        let (solver_left, solver_right) = self.white_range();
+        let nr_of_white_keys = self.nr_of_white_keys(solver_left, solver_right);
+        let nr_of_black_edges = (solver_right - solver_left) - (self.right_key - self.left_key);
+        // For black keys at the edges the neighbouring white keys are laid out, too.
+        // Only a part of them remains visible, which is at least 15% of their width.
+        let solver_width = |width: u16| {
+            width as u32 * 20 * nr_of_white_keys
+                / (20 * nr_of_white_keys - 17 * nr_of_black_edges as u32)
+        };
+        // Three pixels per white key interval like for the original white key ranges.
+        // Short ranges and black keys at the edges need a bit more.
+        let min_width = if !self.pattern.is_twelve_tone() {
+            microtonal::min_width(&self)
+        } else if nr_of_keys < 12 || nr_of_black_edges > 0 {
+            nr_of_keys * 3 + 3
+        } else {
+            (nr_of_keys - 1) * 3
+        };
+        // The height scales with the width and is limited to u16, too.
+        let fits = |width: u16| {
+            let solver_width = solver_width(width);
+            width <= 65535 - 127
+                && solver_width <= 65535 - 127
+                && self.vertical(solver_width).fits()
+        };
+        if !fits(self.width) {
+            let max_width = (min_width..self.width)
+                .rev()
+                .find(|w| fits(*w))
+                .unwrap_or(min_width);
+            Err(KeyboardError::WidthTooBig {
+                width: self.width,
+                max_width,
+            })
+        } else if self.width < min_width {
+            Err(KeyboardError::WidthTooSmall {
+                width: self.width,
+                min_width,
+                nr_of_keys,
+            })
         } else {
             Ok(self)
         }
     }
     /// Define a standard piano with 25/37/49/61/64/73/76 or 88 keys.
-    pub fn standard_piano(mut self, nr_of_keys: u8) -> Result<KeyboardBuilder, String> {
+    /// The key pattern is reset to twelve tone.
+    pub fn standard_piano(mut self, nr_of_keys: u8) -> Result<KeyboardBuilder, KeyboardError> {
         let (left, right) = match nr_of_keys {
             88 => (21, 108),
             76 => (21, 108 - 12),          // one octave less from top
@@ -194,22 +506,21 @@ impl KeyboardBuilder {
             49 => (21 + 12 + 3, 108 - 24), // one octave less from top
             37 => (21 + 24 + 3, 108 - 24), // one octave less from bottom
             25 => (21 + 24 + 3, 108 - 36), // one octave less from top
-            _ => {
-                return Err(format!(
-                    "size {} not a recognized standard size",
-                    nr_of_keys
-                ))
-            }
+            _ => return Err(KeyboardError::UnknownStandardPiano(nr_of_keys)),
         };
         assert_eq!(right - left + 1, nr_of_keys);
-        self.left_white_key = left;
-        self.right_white_key = right;
+        self.pattern = KeyPattern::twelve_tone();
+        self.left_key = left;
+        self.right_key = right;
+        self.key_range_set = true;
         self.check_width()
     }
     pub fn is_rd64(mut self) -> KeyboardBuilder {
         // RD-64 is A1 to C7
-        self.left_white_key = 21 + 12;
-        self.right_white_key = 108 - 12;
+        self.pattern = KeyPattern::twelve_tone();
+        self.left_key = 21 + 12;
+        self.right_key = 108 - 12;
+        self.key_range_set = true;
         self
     }
     /// The keys are defined by MIDI key codes.
@@ -219,30 +530,157 @@ impl KeyboardBuilder {
         mut self,
         left_white_key: u8,
         right_white_key: u8,
-    ) -> Result<KeyboardBuilder, String> {
+    ) -> Result<KeyboardBuilder, KeyboardError> {
         if left_white_key > right_white_key {
-            Err("left white key right from right white key ".to_string())
+            Err(KeyboardError::KeysSwapped {
+                left: left_white_key,
+                right: right_white_key,
+            })
         } else if left_white_key > 127 {
-            Err("left white key is out of range".to_string())
+            Err(KeyboardError::KeyOutOfRange(left_white_key))
         } else if right_white_key > 127 {
-            Err("right white key is out of range".to_string())
-        } else if right_white_key - left_white_key < 11 {
-            Err("Keyboard must be at least one octave".to_string())
-        } else if !KeyboardBuilder::is_white(left_white_key) {
-            Err("left white key is not a white key".to_string())
-        } else if !KeyboardBuilder::is_white(right_white_key) {
-            Err("right white key is not a white key".to_string())
+            Err(KeyboardError::KeyOutOfRange(right_white_key))
+        } else if !self.is_white_key(left_white_key) {
+            Err(KeyboardError::NotAWhiteKey(left_white_key))
+        } else if !self.is_white_key(right_white_key) {
+            Err(KeyboardError::NotAWhiteKey(right_white_key))
         } else {
-            self.left_white_key = left_white_key;
-            self.right_white_key = right_white_key;
+            self.left_key = left_white_key;
+            self.right_key = right_white_key;
+            self.key_range_set = true;
             self.check_width()
         }
     }
+    /// The keys are defined by MIDI key codes in range 0..128.
+    /// In contrast to set_most_left_right_white_keys(), the keyboard may start or end
+    /// with a black key. In this case the neighbouring white key outside of the range is
+    /// only partially visible as blind area without being a key.
+    ///
+    /// Any range of at least one key is accepted, so even a single key can be laid out.
+    /// For key patterns other than twelve tone both keys have to be white keys.
+    pub fn set_most_left_right_keys(
+        mut self,
+        left_key: u8,
+        right_key: u8,
+    ) -> Result<KeyboardBuilder, KeyboardError> {
+        if left_key > right_key {
+            Err(KeyboardError::KeysSwapped {
+                left: left_key,
+                right: right_key,
+            })
+        } else if left_key > 127 {
+            Err(KeyboardError::KeyOutOfRange(left_key))
+        } else if right_key > 127 {
+            Err(KeyboardError::KeyOutOfRange(right_key))
+        } else if !self.pattern.is_twelve_tone() && !self.pattern.is_white(left_key) {
+            Err(KeyboardError::NotAWhiteKey(left_key))
+        } else if !self.pattern.is_twelve_tone() && !self.pattern.is_white(right_key) {
+            Err(KeyboardError::NotAWhiteKey(right_key))
+        } else {
+            self.left_key = left_key;
+            self.right_key = right_key;
+            self.key_range_set = true;
+            self.check_width()
+        }
+    }
+    /// Lay out the keys by the white/black pattern of another octave division,
+    /// e.g. KeyPattern::edo31(). The key codes are the steps of the tuning.
+    ///
+    /// A key range set before is kept, but both keys have to be white keys of the pattern
+    /// unless it is twelve tone. Without a key range set before, the range covers two octaves
+    /// starting with the first white key of the pattern, as far as they fit into the MIDI range.
+    pub fn set_key_pattern(
+        mut self,
+        pattern: KeyPattern,
+    ) -> Result<KeyboardBuilder, KeyboardError> {
+        if !self.key_range_set {
+            let left_key = pattern.first_white();
+            let division = pattern.division();
+            let octaves = ((127 - left_key) / division).min(2);
+            self.left_key = left_key;
+            self.right_key = left_key + octaves * division;
+        } else if !pattern.is_twelve_tone() && !pattern.is_white(self.left_key) {
+            return Err(KeyboardError::NotAWhiteKey(self.left_key));
+        } else if !pattern.is_twelve_tone() && !pattern.is_white(self.right_key) {
+            return Err(KeyboardError::NotAWhiteKey(self.right_key));
+        }
+        self.pattern = pattern;
+        self.check_width()
+    }
     /// Sets the desired keyboard width in pixels.
-    pub fn set_width(mut self, width: u16) -> Result<KeyboardBuilder, String> {
+    /// For a vertical keyboard this is the length along the key axis, which means the height.
+    /// A previous fit_into() is discarded.
+    pub fn set_width(mut self, width: u16) -> Result<KeyboardBuilder, KeyboardError> {
         self.width = width;
+        self.stretch_1024 = 1024;
+        self.fit = None;
         self.check_width()
     }
+    /// Sets the aspect ratio of one pixel (dot) for displays with non-square pixels.
+    /// E.g. a character cell terminal with one pixel per cell may use 1 for the width
+    /// and 2 for the height.
+    ///
+    /// The horizontal dimensions are not affected, while all vertical dimensions
+    /// (key heights and gaps) are scaled accordingly.
+    /// As the height is limited to u16, too, a flat dot may reduce the maximum width.
+    pub fn set_dot_ratio(
+        mut self,
+        dot_width: u16,
+        dot_height: u16,
+    ) -> Result<KeyboardBuilder, KeyboardError> {
+        let error = KeyboardError::InvalidDotRatio {
+            dot_width,
+            dot_height,
+        };
+        if dot_width == 0 || dot_height == 0 {
+            return Err(error);
+        }
+        let ratio_1024 = (dot_height as u32 * 1024 + dot_width as u32 / 2) / dot_width as u32;
+        if ratio_1024 == 0 || ratio_1024 > 65535 {
+            Err(error)
+        } else {
+            self.dot_ratio_1024 = ratio_1024 as u16;
+            self.check_width()
+        }
+    }
+    /// Use a custom profile of physical key dimensions.
+    /// The default profile is KeyDimensions::default().
+    /// A profile set by set_key_dimensions_3d() has to match the new dimensions, too.
+    pub fn set_key_dimensions(
+        mut self,
+        dims: KeyDimensions,
+    ) -> Result<KeyboardBuilder, KeyboardError> {
+        dims.validate()?;
+        if let Some(dims_3d) = self.dims_3d.as_ref() {
+            dims_3d.validate(&dims)?;
+        }
+        self.dims = dims;
+        self.check_width()
+    }
+    /// Use a custom profile of the physical key dimensions needed for build3d().
+    /// The default profile is KeyDimensions3d::default().
+    pub fn set_key_dimensions_3d(
+        mut self,
+        dims_3d: KeyDimensions3d,
+    ) -> Result<KeyboardBuilder, KeyboardError> {
+        dims_3d.validate(&self.dims)?;
+        self.dims_3d = Some(dims_3d);
+        Ok(self)
+    }
+    /// Select horizontal or vertical keyboard layout.
+    /// The width given by set_width() is always fitted pixel exact along the key axis.
+    pub fn set_orientation(mut self, orientation: Orientation) -> KeyboardBuilder {
+        self.orientation = orientation;
+        self
+    }
+    /// Mirror the keyboard horizontally (e.g. right-to-left) and/or vertically (upside-down).
+    /// The mirroring is applied after the orientation.
+    /// The elements are still ordered from low to high keys.
+    pub fn set_mirror(mut self, horizontal: bool, vertical: bool) -> KeyboardBuilder {
+        self.mirror_horizontal = horizontal;
+        self.mirror_vertical = vertical;
+        self
+    }
     pub fn white_black_gap_present(mut self, gap_present: bool) -> KeyboardBuilder {
         self.need_black_gap = gap_present;
         self
@@ -254,47 +692,228 @@ impl KeyboardBuilder {
             _ => panic!("wrong value"),
         }
     }
-    /// Final build the keyboard, which means to perform all calculations and
-    /// create all the elements.
-    ///
-    pub fn build2d(self) -> Keyboard2d {
-        let base = Base::calculate(&self);
-        let top = Top::calculate(&self, &base);
-
-        let base_elements = base.get_elements();
-
-        let nr_of_white_keys = (self.left_white_key..=self.right_white_key)
-            .filter(|k| KeyboardBuilder::is_white(*k))
-            .count() as u16;
+    // Same as is_white(), but for the key pattern of the builder
+    fn is_white_key(&self, key: u8) -> bool {
+        self.pattern.is_white(key)
+    }
+    fn nr_of_white_keys(&self, left_key: u8, right_key: u8) -> u32 {
+        (left_key..=right_key)
+            .filter(|k| self.is_white_key(*k))
+            .count() as u32
+    }
+    // The range of white keys to be laid out. Black keys at the edges are extended
+    // by the neighbouring white key.
+    fn white_range(&self) -> (u8, u8) {
+        let left = if self.is_white_key(self.left_key) {
+            self.left_key
+        } else {
+            self.left_key - 1
+        };
+        let right = if self.is_white_key(self.right_key) {
+            self.right_key
+        } else {
+            self.right_key + 1
+        };
+        (left, right)
+    }
+    // The gaps and key sizes along the key length for a layout of the white key range
+    // with the given width. They are calculated in u32, because the height of a
+    // layout may exceed u16 for big widths or extreme dot ratios.
+    fn vertical(&self, width: u32) -> Vertical {
+        let (left, right) = self.white_range();
+        let nr_of_white_keys = self.nr_of_white_keys(left, right);
 
-        let key_gap_10um = self.white_key_height_10um
-            - self.black_key_height_10um
-            - self.white_key_wide_height_10um;
+        let key_gap_10um = self.dims.key_gap_10um();
 
         // left and right from the outer keys have a gap, too
-        let keyboard_width_10um = (self.white_key_wide_width_10um + key_gap_10um)
-            * nr_of_white_keys as u32
-            + key_gap_10um;
+        let keyboard_width_10um =
+            (self.dims.white_key_wide_width_10um + key_gap_10um) * nr_of_white_keys + key_gap_10um;
 
-        let key_gap = ((self.width as u32 * key_gap_10um + keyboard_width_10um / 2)
-            / keyboard_width_10um) as u16;
+        let key_gap = (width * key_gap_10um + keyboard_width_10um / 2) / keyboard_width_10um;
         let black_gap = if self.need_black_gap { key_gap } else { 0 };
 
-        let max_pure_white_key_width = self.width - key_gap * (nr_of_white_keys + 1) as u16;
+        let max_pure_white_key_width = width.saturating_sub(key_gap * (nr_of_white_keys + 1));
 
         let white_key_wide_width = max_pure_white_key_width / nr_of_white_keys;
 
+        // All vertical dimensions are scaled by the dot ratio.
+        // Gaps are kept visible even for very flat dots.
+        // For a vertical keyboard the key depth is horizontal, so the ratio is inverted.
+        let dot_ratio_1024 = if self.orientation == Orientation::Horizontal {
+            self.dot_ratio_1024 as u64
+        } else {
+            (1024 * 1024 + self.dot_ratio_1024 as u64 / 2) / self.dot_ratio_1024 as u64
+        };
+        let to_vertical = |v: u32| ((v as u64 * 1024 + dot_ratio_1024 / 2) / dot_ratio_1024) as u32;
+        let key_gap_v = to_vertical(key_gap).max(key_gap.min(1));
+        let black_gap_v = to_vertical(black_gap).max(black_gap.min(1));
         let black_key_height =
-            ((white_key_wide_width as u64 * self.black_key_height_10um as u64 * 1024
-                + self.white_key_wide_width_10um as u64 / 2)
-                / self.white_key_wide_width_10um as u64
-                / self.dot_ratio_1024 as u64) as u16;
-        let white_key_wide_height = ((white_key_wide_width as u64
-            * self.white_key_wide_height_10um as u64
-            + self.white_key_wide_width_10um as u64 / 2)
-            / self.white_key_wide_width_10um as u64) as u16;
+            (white_key_wide_width as u64 * self.dims.black_key_height_10um as u64 * 1024
+                + self.dims.white_key_wide_width_10um as u64 / 2)
+                / self.dims.white_key_wide_width_10um as u64
+                / dot_ratio_1024;
+        let white_key_wide_height =
+            (white_key_wide_width as u64 * self.dims.white_key_wide_height_10um as u64 * 1024
+                / dot_ratio_1024
+                + self.dims.white_key_wide_width_10um as u64 / 2)
+                / self.dims.white_key_wide_width_10um as u64;
+        // The key lengths may be stretched by fit_into()
+        let stretch = |v: u64| (v * self.stretch_1024 as u64 / 1024).min(u32::MAX as u64) as u32;
+
+        Vertical {
+            key_gap,
+            white_key_wide_width,
+            key_gap_v,
+            black_gap_v,
+            black_key_height: stretch(black_key_height),
+            white_key_wide_height: stretch(white_key_wide_height),
+        }
+    }
+    // The maximum width of a layout of the white key range, which fits into u16.
+    fn layout_max_width(&self) -> u16 {
+        (0..self.width)
+            .rev()
+            .find(|w| self.vertical(*w as u32).fits())
+            .unwrap_or(0)
+    }
+    // Returns the builder for the layout calculation with white keys at both ends
+    // and the number of pixels to be cropped on the left side of its layout.
+    //
+    // The layout is cropped one gap outside of a black key at the edge. As the crop
+    // depends on the layout, the width of the layout is iterated until the cropped
+    // keyboard fits the requested width.
+    fn solver(&self) -> Result<(KeyboardBuilder, u16), KeyboardError> {
+        let mut solver = self.clone();
+        let (left, right) = self.white_range();
+        solver.left_key = left;
+        solver.right_key = right;
+        if left == self.left_key && right == self.right_key {
+            return Ok((solver, 0));
+        }
+
+        // Initial guess: the cropped neighbours keep about half of their width
+        let nr_of_white_keys = self.nr_of_white_keys(left, right);
+        let nr_of_black_edges = ((right - left) - (self.right_key - self.left_key)) as u32;
+        let mut width =
+            self.width as u32 * 2 * nr_of_white_keys / (2 * nr_of_white_keys - nr_of_black_edges);
+        let mut crop_left = 0;
+        for _ in 0..10 {
+            solver.width = width as u16;
+            let (elements, _, _) = solver.layout()?;
+            crop_left = 0;
+            let mut crop_right = 0;
+            if left != self.left_key {
+                let outter_gap = elements[0].start();
+                crop_left = elements[1].start() - outter_gap;
+            }
+            if right != self.right_key {
+                let n = elements.len();
+                let outter_gap = solver.width - elements[n - 1].end();
+                crop_right = solver.width - elements[n - 2].end() - outter_gap;
+            }
+            let needed = self.width as u32 + crop_left as u32 + crop_right as u32;
+            if needed == width {
+                break;
+            }
+            width = needed;
+        }
+        Ok((solver, crop_left))
+    }
+    /// Final build the keyboard, which means to perform all calculations and
+    /// create all the elements.
+    ///
+    /// Panics, if the layout calculation fails. Use try_build2d() to get an error instead.
+    pub fn build2d(self) -> Keyboard2d {
+        match self.try_build2d() {
+            Ok(keyboard) => keyboard,
+            Err(e) => panic!("{}", e),
+        }
+    }
+    /// Same as build2d(), but any failure of the layout calculation is returned as error.
+    pub fn try_build2d(self) -> Result<Keyboard2d, KeyboardError> {
+        // The orientation may have inverted the dot ratio since the last check.
+        let builder = self.check_width()?;
+        let (solver, crop_left) = builder.solver()?;
+        let (elements, height, report) = solver.layout()?;
+        Ok(builder.assemble(elements, height, report, crop_left))
+    }
+    // Create the keyboard from a layout, which may cover more keys than the key range.
+    // The key range is cut out starting at crop_left with the builder's width.
+    fn assemble(
+        &self,
+        elements: Vec<Element>,
+        height: u16,
+        report: LayoutReport,
+        crop_left: u16,
+    ) -> Keyboard2d {
+        // Shift the layout by the cropped pixels. The parts of the neighbouring
+        // white keys outside of the key range remain as blind areas.
+        let crop = |r: &Rectangle| {
+            let start = r.x.clamp(crop_left, crop_left + self.width);
+            let end = (r.x + r.width).min(crop_left + self.width).max(start);
+            Rectangle {
+                x: start - crop_left,
+                y: r.y,
+                width: end - start,
+                height: r.height,
+            }
+        };
+        let mut blinds = vec![];
+        let mut visible = vec![];
+        for el in elements.into_iter() {
+            if (self.left_key..=self.right_key).contains(&el.key()) {
+                visible.push(el.map_rects(crop));
+            } else {
+                blinds.extend(el.rects().into_iter().map(crop).filter(|r| r.width > 0));
+            }
+        }
 
-        let height = 2 * key_gap + black_gap + black_key_height + white_key_wide_height;
+        let transform = Transform::new(self.orientation, self.width, height)
+            .mirrored(self.mirror_horizontal, self.mirror_vertical);
+        let elements = visible
+            .into_iter()
+            .map(|el| el.map_rects(|r| transform.rect(r)))
+            .collect::<Vec<_>>();
+        let blinds = blinds.iter().map(|r| transform.rect(r)).collect::<Vec<_>>();
+        let (width, height) = transform.size();
+
+        Keyboard2d {
+            left_white_key: self.left_key,
+            right_white_key: self.right_key,
+            width,
+            height,
+            report,
+            elements,
+            blinds,
+            transform,
+            fit: self.fit,
+            pattern: self.pattern.clone(),
+        }
+    }
+    // Calculate the horizontal layout for a keyboard with white keys at both ends.
+    // Returns the elements, the height and the report of the applied compromises.
+    fn layout(&self) -> Result<(Vec<Element>, u16, LayoutReport), KeyboardError> {
+        let vertical = self.vertical(self.width as u32);
+        if vertical.height() > 65535 {
+            return Err(KeyboardError::WidthTooBig {
+                width: self.width,
+                max_width: self.layout_max_width(),
+            });
+        }
+        if !self.pattern.is_twelve_tone() {
+            return microtonal::layout(self, &vertical);
+        }
+
+        let base = Base::calculate(self)?;
+        let top = Top::calculate(self, &base)?;
+
+        let base_elements = base.get_elements();
+
+        let key_gap_v = vertical.key_gap_v as u16;
+        let black_gap_v = vertical.black_gap_v as u16;
+        let black_key_height = vertical.black_key_height as u16;
+        let white_key_wide_height = vertical.white_key_wide_height as u16;
+        let height = vertical.height() as u16;
 
         let mut elements = vec![];
 
@@ -302,28 +921,28 @@ impl KeyboardBuilder {
         let n = base_elements.len() - 1;
         for (i, el) in base_elements.into_iter().enumerate() {
             match el {
-                base::ResultElement::Key(width, _key) => {
+                base::ResultElement::Key(width, key) => {
                     let wide_rect = Rectangle {
                         x: white_x,
-                        y: black_gap + black_key_height + key_gap,
-                        width: width,
+                        y: black_gap_v + black_key_height + key_gap_v,
+                        width,
                         height: white_key_wide_height,
                     };
-                    let tr = top.get_top_for(&el);
+                    let tr = top.get_top_for(&el)?;
                     match tr {
                         TopResultElement::WhiteGapBlack(w, _g, _blk) => {
                             let small_rect = Rectangle {
                                 x: white_x,
-                                y: key_gap,
+                                y: key_gap_v,
                                 width: w,
-                                height: black_gap + black_key_height,
+                                height: black_gap_v + black_key_height,
                             };
                             let opt_blind = if i == n - 1 {
                                 Some(Rectangle {
                                     x: white_x + w,
-                                    y: key_gap,
+                                    y: key_gap_v,
                                     width: width - w,
-                                    height: black_gap + black_key_height,
+                                    height: black_gap_v + black_key_height,
                                 })
                             } else {
                                 None
@@ -332,59 +951,69 @@ impl KeyboardBuilder {
                                 wide: wide_rect,
                                 small: small_rect,
                                 blind: opt_blind,
+                                key,
                             });
                         }
-                        TopResultElement::BlindWhiteGapBlack(blind, w, g, _blk) => {
+                        TopResultElement::BlindWhiteGapBlack(blind, w, _g, _blk) => {
                             let opt_blind = if i == 1 {
                                 Some(Rectangle {
                                     x: white_x,
-                                    y: key_gap,
+                                    y: key_gap_v,
                                     width: blind,
-                                    height: black_gap + black_key_height,
+                                    height: black_gap_v + black_key_height,
                                 })
                             } else if i == n - 1 {
                                 Some(Rectangle {
-                                    x: white_x + w + g,
-                                    y: key_gap,
-                                    width: width - w - g,
-                                    height: black_gap + black_key_height,
+                                    x: white_x + blind + w,
+                                    y: key_gap_v,
+                                    width: width - blind - w,
+                                    height: black_gap_v + black_key_height,
                                 })
                             } else {
                                 None
                             };
+                            // A single white key has no black neighbour at all,
+                            // so the small part extends to the right end.
+                            let w = if i == 1 && i == n - 1 {
+                                width - blind
+                            } else {
+                                w
+                            };
                             let small_rect = Rectangle {
                                 x: white_x + blind,
-                                y: key_gap,
+                                y: key_gap_v,
                                 width: w,
-                                height: black_gap + black_key_height,
+                                height: black_gap_v + black_key_height,
                             };
                             elements.push(Element::WhiteKey {
                                 wide: wide_rect,
                                 small: small_rect,
                                 blind: opt_blind,
+                                key,
                             });
                         }
                         TopResultElement::BlindWhite(g, w) => {
                             let opt_blind = if i == 1 {
                                 Some(Rectangle {
                                     x: white_x,
-                                    y: key_gap,
+                                    y: key_gap_v,
                                     width: g,
-                                    height: black_gap + black_key_height,
+                                    height: black_gap_v + black_key_height,
                                 })
                             } else {
                                 None
                             };
                             let small_rect = Rectangle {
                                 x: white_x + g,
-                                y: key_gap,
+                                y: key_gap_v,
                                 width: w,
-                                height: black_gap + black_key_height,
+                                height: black_gap_v + black_key_height,
                             };
                             elements.push(Element::WhiteKey {
                                 wide: wide_rect,
                                 small: small_rect,
                                 blind: opt_blind,
+                                key,
                             });
                         }
                     };
@@ -393,20 +1022,20 @@ impl KeyboardBuilder {
                             TopResultElement::WhiteGapBlack(w, g, blk) => {
                                 let rect = Rectangle {
                                     x: white_x + w + g,
-                                    y: key_gap,
+                                    y: key_gap_v,
                                     width: blk,
                                     height: black_key_height,
                                 };
-                                elements.push(Element::BlackKey(rect));
+                                elements.push(Element::BlackKey(rect, key + 1));
                             }
                             TopResultElement::BlindWhiteGapBlack(blind, w, g, blk) => {
                                 let rect = Rectangle {
                                     x: white_x + blind + w + g,
-                                    y: key_gap,
+                                    y: key_gap_v,
                                     width: blk,
                                     height: black_key_height,
                                 };
-                                elements.push(Element::BlackKey(rect));
+                                elements.push(Element::BlackKey(rect, key + 1));
                             }
                             TopResultElement::BlindWhite(_g, _w) => (),
                         }
@@ -421,22 +1050,32 @@ impl KeyboardBuilder {
 
         //println!("{:#?}", elements);
 
-        Keyboard2d {
-            left_white_key: self.left_white_key,
-            right_white_key: self.right_white_key,
-            width: self.width,
-            height,
-            perfect: base.is_perfect() && top.is_perfect(),
-            elements,
+        let mut report = base.report();
+        report.black_key_width = top.black_key_width();
+        let gs_enlarged = top.black_gs_enlarged();
+        if gs_enlarged > 0 {
+            for el in elements.iter() {
+                if let Element::BlackKey(_, key) = el {
+                    if key % 12 == 8 {
+                        report.adjustments.push(Adjustment {
+                            kind: AdjustmentKind::BlackKeysGs,
+                            target: Target::Key(*key),
+                            pixels: gs_enlarged,
+                        });
+                    }
+                }
+            }
         }
+
+        Ok((elements, height, report))
     }
 }
 #[cfg(test)]
 mod tests {
-    use crate::KeyboardBuilder;
+    use crate::{KeyboardBuilder, KeyboardError};
 
     #[test]
-    fn test_standard_pianos() -> Result<(), String> {
+    fn test_standard_pianos() -> Result<(), KeyboardError> {
         let _keyboard = KeyboardBuilder::new()
             .standard_piano(88)?
             .standard_piano(76)?
@@ -452,6 +1091,329 @@ mod tests {
         Ok(())
     }
     #[test]
+    fn test_element_keys() {
+        let keyboard = KeyboardBuilder::new()
+            .standard_piano(88)
+            .unwrap()
+            .set_width(800)
+            .unwrap()
+            .build2d();
+        for (el, key) in keyboard.iter().zip(21..=108) {
+            assert_eq!(el.key(), key);
+            assert_eq!(el.is_white(), KeyboardBuilder::is_white(key));
+        }
+        assert_eq!(keyboard.key_pattern().pitch_class(60), 0);
+        assert_eq!(keyboard.key_pattern().octave(60), 4);
+        assert!(keyboard.element(20).is_none());
+        assert!(keyboard.element(109).is_none());
+    }
+    #[test]
+    fn test_key_at() {
+        use crate::{Element, Hit};
+        for width in 200..1000 {
+            let keyboard = KeyboardBuilder::new()
+                .standard_piano(61)
+                .unwrap()
+                .set_width(width)
+                .unwrap()
+                .build2d();
+            assert_eq!(keyboard.key_at(width, 0), Hit::Outside);
+            assert_eq!(keyboard.key_at(0, keyboard.height), Hit::Outside);
+            for el in keyboard.iter() {
+                let rects = match el {
+                    Element::WhiteKey {
+                        wide, small, blind, ..
+                    } => vec![Some(wide), Some(small), blind.as_ref()],
+                    Element::BlackKey(r, _) => vec![Some(r)],
+                };
+                for r in rects.into_iter().flatten() {
+                    for x in r.x..r.x + r.width {
+                        for y in [r.y, r.y + r.height - 1] {
+                            assert_eq!(keyboard.key_at(x, y), Hit::Key(el.key()));
+                        }
+                    }
+                }
+            }
+            // The outter gap is never part of a key
+            assert_eq!(keyboard.key_at(0, keyboard.height - 1), Hit::Gap);
+        }
+    }
+    #[test]
+    fn test_dot_ratio() {
+        let square = KeyboardBuilder::new().set_width(800).unwrap().build2d();
+        let tall = KeyboardBuilder::new()
+            .set_width(800)
+            .unwrap()
+            .set_dot_ratio(1, 2)
+            .unwrap()
+            .build2d();
+        assert_eq!(square.width, tall.width);
+        assert!(tall.height * 2 <= square.height + 4);
+        assert!(tall.height * 2 + 4 >= square.height);
+        assert!(KeyboardBuilder::new().set_dot_ratio(0, 1).is_err());
+        assert!(KeyboardBuilder::new().set_dot_ratio(1, 100).is_err());
+    }
+    #[test]
+    fn test_key_dimensions() {
+        use crate::KeyDimensions;
+        let default = KeyboardBuilder::new().set_width(800).unwrap().build2d();
+        let narrow = KeyboardBuilder::new()
+            .set_width(800)
+            .unwrap()
+            .set_key_dimensions(KeyDimensions {
+                white_key_wide_width_10um: 20_00,
+                ..KeyDimensions::default()
+            })
+            .unwrap()
+            .build2d();
+        assert_eq!(default.width, narrow.width);
+        assert!(default.height < narrow.height);
+        assert!(KeyboardBuilder::new()
+            .set_key_dimensions(KeyDimensions {
+                black_key_height_10um: 85_00,
+                ..KeyDimensions::default()
+            })
+            .is_err());
+
+        // The 3d profile is checked against new key dimensions
+        use crate::{DimensionError, KeyDimensions3d};
+        let short = KeyDimensions {
+            white_key_wide_height_10um: 35_00,
+            ..KeyDimensions::default()
+        };
+        assert!(KeyboardBuilder::new()
+            .set_key_dimensions(short.clone())
+            .is_ok());
+        let deep_lip = KeyDimensions3d {
+            front_lip_depth_10um: 40_00,
+            ..KeyDimensions3d::default()
+        };
+        assert!(matches!(
+            KeyboardBuilder::new()
+                .set_key_dimensions_3d(deep_lip)
+                .unwrap()
+                .set_key_dimensions(short),
+            Err(KeyboardError::InvalidKeyDimensions(
+                DimensionError::LipTooDeep { .. }
+            ))
+        ));
+    }
+    #[test]
+    fn test_orientation() {
+        use crate::{Hit, Orientation};
+        let horizontal = KeyboardBuilder::new()
+            .standard_piano(25)
+            .unwrap()
+            .set_width(500)
+            .unwrap()
+            .build2d();
+        for orientation in [
+            Orientation::VerticalLowAtBottom,
+            Orientation::VerticalLowAtTop,
+        ] {
+            let vertical = KeyboardBuilder::new()
+                .standard_piano(25)
+                .unwrap()
+                .set_width(500)
+                .unwrap()
+                .set_orientation(orientation)
+                .build2d();
+            assert_eq!(vertical.orientation(), orientation);
+            assert_eq!(vertical.width, horizontal.height);
+            assert_eq!(vertical.height, 500);
+            for x in 0..horizontal.width {
+                for y in 0..horizontal.height {
+                    let vy = match orientation {
+                        Orientation::VerticalLowAtBottom => 499 - x,
+                        _ => x,
+                    };
+                    assert_eq!(horizontal.key_at(x, y), vertical.key_at(y, vy));
+                }
+            }
+        }
+        let vertical = KeyboardBuilder::new()
+            .standard_piano(25)
+            .unwrap()
+            .set_orientation(Orientation::VerticalLowAtBottom)
+            .set_width(500)
+            .unwrap()
+            .build2d();
+        assert_eq!(vertical.key_at(vertical.width - 5, 490), Hit::Key(48));
+        assert_eq!(vertical.key_at(vertical.width - 5, 10), Hit::Key(72));
+    }
+    #[test]
+    fn test_mirror() {
+        let plain = KeyboardBuilder::new()
+            .standard_piano(37)
+            .unwrap()
+            .set_width(601)
+            .unwrap()
+            .build2d();
+        for (horizontal, vertical) in [(true, false), (false, true), (true, true)] {
+            let mirrored = KeyboardBuilder::new()
+                .standard_piano(37)
+                .unwrap()
+                .set_width(601)
+                .unwrap()
+                .set_mirror(horizontal, vertical)
+                .build2d();
+            assert_eq!(mirrored.mirror(), (horizontal, vertical));
+            assert_eq!(plain.is_perfect(), mirrored.is_perfect());
+            assert_eq!(
+                (plain.width, plain.height),
+                (mirrored.width, mirrored.height)
+            );
+            for (a, b) in plain.iter().zip(mirrored.iter()) {
+                assert_eq!(a.key(), b.key());
+            }
+            for x in 0..plain.width {
+                for y in 0..plain.height {
+                    let mx = if horizontal { plain.width - 1 - x } else { x };
+                    let my = if vertical { plain.height - 1 - y } else { y };
+                    assert_eq!(plain.key_at(x, y), mirrored.key_at(mx, my));
+                }
+            }
+        }
+    }
+    #[test]
+    fn test_black_key_edges() {
+        use crate::{Element, Hit};
+        for left in 48..60 {
+            for right in [left + 12, left + 13, left + 16, left + 30] {
+                for width in (4 * (right - left) as u16..2000).step_by(7) {
+                    let keyboard = KeyboardBuilder::new()
+                        .set_most_left_right_keys(left, right)
+                        .unwrap()
+                        .set_width(width)
+                        .unwrap()
+                        .build2d();
+                    assert_eq!(keyboard.width, width);
+                    assert_eq!(keyboard.iter().count(), (right - left + 1) as usize);
+                    for (el, key) in keyboard.iter().zip(left..=right) {
+                        assert_eq!(el.key(), key);
+                        for r in el.rects() {
+                            assert!(r.x + r.width <= width);
+                        }
+                    }
+                    // The black keys at the edges are completely visible
+                    let first = keyboard.element(left).unwrap();
+                    let last = keyboard.element(right).unwrap();
+                    if let Element::BlackKey(r, _) = first {
+                        assert_eq!(keyboard.key_at(r.x, r.y), Hit::Key(left));
+                    }
+                    if let Element::BlackKey(r, _) = last {
+                        assert_eq!(keyboard.key_at(r.x + r.width - 1, r.y), Hit::Key(right));
+                    }
+                    // Blind areas are no keys
+                    let nr_of_blinds = keyboard.blinds.len();
+                    for r in keyboard.blinds.iter() {
+                        assert_eq!(keyboard.key_at(r.x, r.y), Hit::Gap);
+                    }
+                    if first.is_white() && last.is_white() {
+                        assert_eq!(nr_of_blinds, 0);
+                    } else if width >= 8 * (right - left) as u16 {
+                        assert!(nr_of_blinds > 0);
+                    }
+                }
+            }
+        }
+    }
+    #[test]
+    fn test_short_ranges() {
+        use crate::Hit;
+        for left in 48..60 {
+            for right in left..left + 12 {
+                let nr_of_keys = (right - left + 1) as u16;
+                for width in 3 * nr_of_keys + 3..400 {
+                    let keyboard = KeyboardBuilder::new()
+                        .set_most_left_right_keys(left, right)
+                        .unwrap()
+                        .set_width(width)
+                        .unwrap()
+                        .build2d();
+                    assert_eq!(keyboard.width, width);
+                    for (el, key) in keyboard.iter().zip(left..=right) {
+                        assert_eq!(el.key(), key);
+                        for r in el.rects() {
+                            assert!(r.x + r.width <= width);
+                        }
+                    }
+                    assert_eq!(keyboard.iter().count(), nr_of_keys as usize);
+                    for r in keyboard.white_keys(false).iter() {
+                        if r.width > 0 && r.height > 0 {
+                            assert!(matches!(keyboard.key_at(r.x, r.y), Hit::Key(_)));
+                        }
+                    }
+                }
+            }
+        }
+        assert!(KeyboardBuilder::new()
+            .set_most_left_right_keys(60, 60)
+            .unwrap()
+            .set_width(5)
+            .is_err());
+    }
+    #[test]
+    fn test_try_build2d() -> Result<(), KeyboardError> {
+        for left in 21..33 {
+            for right in [left, left + 5, left + 11, left + 24] {
+                for width in (3 * (right - left + 2) as u16..3000).step_by(11) {
+                    let keyboard = KeyboardBuilder::new()
+                        .set_most_left_right_keys(left, right)?
+                        .set_width(width)?
+                        .try_build2d()?;
+                    assert_eq!(keyboard.width, width);
+                }
+            }
+        }
+        Ok(())
+    }
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            KeyboardBuilder::new().standard_piano(62).err(),
+            Some(KeyboardError::UnknownStandardPiano(62))
+        );
+        assert_eq!(
+            KeyboardBuilder::new().set_width(100).err(),
+            Some(KeyboardError::WidthTooSmall {
+                width: 100,
+                min_width: 261,
+                nr_of_keys: 88
+            })
+        );
+        // The lower limit of the original white key ranges is kept
+        assert!(KeyboardBuilder::new()
+            .set_width(261)
+            .unwrap()
+            .try_build2d()
+            .is_ok());
+        assert_eq!(
+            KeyboardBuilder::new()
+                .set_most_left_right_white_keys(61, 72)
+                .err(),
+            Some(KeyboardError::NotAWhiteKey(61))
+        );
+        assert_eq!(
+            KeyboardBuilder::new()
+                .set_most_left_right_keys(72, 60)
+                .err(),
+            Some(KeyboardError::KeysSwapped {
+                left: 72,
+                right: 60
+            })
+        );
+        assert!(matches!(
+            KeyboardBuilder::new()
+                .set_most_left_right_keys(61, 70)
+                .unwrap()
+                .set_width(65000),
+            Err(KeyboardError::WidthTooBig { max_width, .. }) if max_width < 65000
+        ));
+        let e: Box<dyn std::error::Error> = Box::new(KeyboardError::KeyOutOfRange(128));
+        assert_eq!(e.to_string(), "key 128 is out of range");
+    }
+    #[test]
     fn test_max_width() {
         let _keyboard = KeyboardBuilder::new()
             .set_most_left_right_white_keys(0, 127)
@@ -461,6 +1423,84 @@ mod tests {
             .build2d();
     }
     #[test]
+    fn test_max_width_by_height() {
+        let builders = vec![
+            KeyboardBuilder::new().set_most_left_right_keys(60, 60),
+            KeyboardBuilder::new().set_most_left_right_keys(61, 61),
+            KeyboardBuilder::new().set_most_left_right_keys(60, 62),
+            KeyboardBuilder::new().set_most_left_right_keys(60, 64),
+            KeyboardBuilder::new().set_dot_ratio(64, 1),
+        ];
+        for builder in builders.into_iter().map(Result::unwrap) {
+            let max_width = match builder.clone().set_width(65000) {
+                Err(KeyboardError::WidthTooBig { max_width, .. }) => max_width,
+                other => panic!("unexpected {:?}", other.err()),
+            };
+            let keyboard = builder
+                .clone()
+                .set_width(max_width)
+                .unwrap()
+                .try_build2d()
+                .unwrap();
+            assert_eq!(keyboard.width, max_width);
+            assert_eq!(
+                builder.set_width(max_width + 1).err(),
+                Some(KeyboardError::WidthTooBig {
+                    width: max_width + 1,
+                    max_width
+                })
+            );
+        }
+        assert_eq!(
+            KeyboardBuilder::new()
+                .set_width(20000)
+                .unwrap()
+                .set_dot_ratio(64, 1)
+                .err(),
+            Some(KeyboardError::WidthTooBig {
+                width: 20000,
+                max_width: 9733
+            })
+        );
+    }
+    #[test]
+    fn test_height_overflow() {
+        use crate::Orientation;
+        // Bypass check_width() to exercise the layout at the boundary widths
+        let layout = |left_key: u8, right_key: u8, width: u16| {
+            let mut builder = KeyboardBuilder::new()
+                .set_most_left_right_keys(left_key, right_key)
+                .unwrap();
+            builder.width = width;
+            builder.layout().map(|(_, height, _)| height)
+        };
+        for (left_key, right_key, width) in [(60, 60, 12562), (60, 62, 24478), (60, 64, 36394)] {
+            assert!(layout(left_key, right_key, width - 1).is_ok());
+            assert!(matches!(
+                layout(left_key, right_key, width),
+                Err(KeyboardError::WidthTooBig { max_width, .. }) if max_width < width
+            ));
+        }
+        let mut builder = KeyboardBuilder::new().set_dot_ratio(64, 1).unwrap();
+        builder.width = 20000;
+        assert!(matches!(
+            builder.layout(),
+            Err(KeyboardError::WidthTooBig { .. })
+        ));
+
+        // The orientation inverts the dot ratio after set_dot_ratio()
+        let builder = KeyboardBuilder::new()
+            .set_width(20000)
+            .unwrap()
+            .set_dot_ratio(1, 32)
+            .unwrap()
+            .set_orientation(Orientation::VerticalLowAtTop);
+        assert!(matches!(
+            builder.try_build2d(),
+            Err(KeyboardError::WidthTooBig { width: 20000, .. })
+        ));
+    }
+    #[test]
     fn test_several_widths() {
         for width in 65000..65535 - 127 {
             let _keyboard = KeyboardBuilder::new()
@@ -476,7 +1516,7 @@ mod tests {
     //      cargo test -- --ignored
     #[test]
     #[ignore]
-    fn test_all_pianos() -> Result<(), String> {
+    fn test_all_pianos() -> Result<(), KeyboardError> {
         for keys in vec![25, 37, 49, 61, 64, 73, 76, 88].into_iter() {
             for width in 3 * keys as u16..65535 - 127 {
                 let _keyboard = KeyboardBuilder::new()
@@ -0,0 +1,278 @@
+//! Several keyboards stacked vertically like the manuals of an organ console.
+//!
+//! All manuals are cut out of one common layout for the combined key range.
+//! So every key has the same width and position in all manuals containing it,
+//! before the optional horizontal offset of a manual is applied.
+use crate::{Hit, Keyboard2d, KeyboardBuilder, KeyboardError, Orientation};
+
+/// One manual of a multi-manual arrangement.
+pub struct Manual {
+    /// Position of the upper left corner within the arrangement
+    pub x: u16,
+    pub y: u16,
+    /// The keyboard with coordinates relative to x/y
+    pub keyboard: Keyboard2d,
+}
+impl Manual {
+    /// Hit test with coordinates of the arrangement.
+    pub fn key_at(&self, x: u16, y: u16) -> Hit {
+        if x < self.x || y < self.y {
+            return Hit::Outside;
+        }
+        self.keyboard.key_at(x - self.x, y - self.y)
+    }
+}
+
+/// The arrangement as returned by ManualsBuilder::build().
+pub struct Manuals {
+    pub width: u16,
+    pub height: u16,
+    manuals: Vec<Manual>,
+}
+impl Manuals {
+    /// The manuals from top to bottom in the sequence they have been added.
+    pub fn iter(&self) -> std::slice::Iter<'_, Manual> {
+        self.manuals.iter()
+    }
+    pub fn manual(&self, index: usize) -> Option<&Manual> {
+        self.manuals.get(index)
+    }
+    /// Determine the manual index and the key under the pixel at x/y.
+    /// Returns None, if the pixel is outside of all manuals.
+    pub fn key_at(&self, x: u16, y: u16) -> Option<(usize, Hit)> {
+        self.manuals
+            .iter()
+            .enumerate()
+            .map(|(i, m)| (i, m.key_at(x, y)))
+            .find(|(_, hit)| *hit != Hit::Outside)
+    }
+}
+
+#[derive(Clone)]
+struct ManualRange {
+    left_key: u8,
+    right_key: u8,
+    offset: i16,
+}
+
+/// Builder for a multi-manual arrangement.
+///
+/// The width of the given KeyboardBuilder applies to the combined key range of all manuals.
+/// Its key range is ignored and the manuals are always horizontal.
+#[derive(Clone)]
+pub struct ManualsBuilder {
+    keyboard: KeyboardBuilder,
+    manuals: Vec<ManualRange>,
+    spacing: u16,
+}
+impl ManualsBuilder {
+    pub fn new(keyboard: KeyboardBuilder) -> ManualsBuilder {
+        ManualsBuilder {
+            keyboard,
+            manuals: vec![],
+            spacing: 0,
+        }
+    }
+    /// Add a manual below the previously added manuals.
+    pub fn add_manual(self, left_key: u8, right_key: u8) -> Result<ManualsBuilder, KeyboardError> {
+        self.add_manual_with_offset(left_key, right_key, 0)
+    }
+    /// Add a manual shifted horizontally by offset pixels. Positive values shift to the right.
+    pub fn add_manual_with_offset(
+        mut self,
+        left_key: u8,
+        right_key: u8,
+        offset: i16,
+    ) -> Result<ManualsBuilder, KeyboardError> {
+        if left_key > right_key {
+            return Err(KeyboardError::KeysSwapped {
+                left: left_key,
+                right: right_key,
+            });
+        }
+        if right_key > 127 {
+            return Err(KeyboardError::KeyOutOfRange(right_key));
+        }
+        self.manuals.push(ManualRange {
+            left_key,
+            right_key,
+            offset,
+        });
+        Ok(self)
+    }
+    /// Vertical space in pixels between two manuals.
+    pub fn set_spacing(mut self, spacing: u16) -> ManualsBuilder {
+        self.spacing = spacing;
+        self
+    }
+    pub fn build(self) -> Result<Manuals, KeyboardError> {
+        let left_key = self.manuals.iter().map(|m| m.left_key).min();
+        let right_key = self.manuals.iter().map(|m| m.right_key).max();
+        let (left_key, right_key) = match (left_key, right_key) {
+            (Some(left), Some(right)) => (left, right),
+            _ => return Err(KeyboardError::NoManuals),
+        };
+        let mut common = self
+            .keyboard
+            .clone()
+            .set_most_left_right_keys(left_key, right_key)?;
+        common.orientation = Orientation::Horizontal;
+        common.mirror_horizontal = false;
+        common.mirror_vertical = false;
+        let (solver, crop_left) = common.solver()?;
+        let (elements, height, report) = solver.layout()?;
+
+        // Extent of a manual within the common layout. A manual ends at its neighbouring
+        // white keys including the gap to them. At a black key one gap is added like for
+        // a single keyboard.
+        let key_gap = report.key_gap;
+        let element = |key: u8| &elements[(key - solver.left_key) as usize];
+        let extent = |m: &ManualRange| {
+            let start = if m.left_key == left_key {
+                crop_left
+            } else if common.is_white_key(m.left_key) {
+                let idx = (m.left_key - solver.left_key) as usize;
+                elements[..idx]
+                    .iter()
+                    .rev()
+                    .find(|el| el.is_white())
+                    .map(|el| el.end())
+                    .unwrap_or(crop_left)
+            } else {
+                element(m.left_key).start() - key_gap
+            };
+            let end = if m.right_key == right_key {
+                crop_left + common.width
+            } else if common.is_white_key(m.right_key) {
+                let idx = (m.right_key - solver.left_key) as usize;
+                elements[idx + 1..]
+                    .iter()
+                    .find(|el| el.is_white())
+                    .map(|el| el.start())
+                    .unwrap_or(crop_left + common.width)
+            } else {
+                element(m.right_key).end() + key_gap
+            };
+            (start, end)
+        };
+
+        let extents = self.manuals.iter().map(extent).collect::<Vec<_>>();
+        let min_x = self
+            .manuals
+            .iter()
+            .zip(extents.iter())
+            .map(|(m, (start, _))| *start as i32 + m.offset as i32)
+            .min()
+            .unwrap_or(0);
+
+        let mut manuals = vec![];
+        let mut y = 0;
+        for (m, (start, end)) in self.manuals.iter().zip(extents) {
+            let mut kb = common.clone();
+            kb.left_key = m.left_key;
+            kb.right_key = m.right_key;
+            kb.width = end - start;
+            // Only the adjustments of the keys and gaps within the manual are relevant
+            let (first, last) = kb.white_range();
+            let mut report = report.clone();
+            report
+                .adjustments
+                .retain(|a| (first..=last).contains(&a.target.key()));
+            let keyboard = kb.assemble(elements.clone(), height, report, start);
+            manuals.push(Manual {
+                x: (start as i32 + m.offset as i32 - min_x) as u16,
+                y,
+                keyboard,
+            });
+            y += height + self.spacing;
+        }
+
+        Ok(Manuals {
+            width: manuals
+                .iter()
+                .map(|m| m.x + m.keyboard.width)
+                .max()
+                .unwrap_or(0),
+            height: y - self.spacing,
+            manuals,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Element, Hit, KeyboardBuilder, KeyboardError, ManualsBuilder};
+
+    fn wide_x(m: &crate::Manual, key: u8) -> u16 {
+        match m.keyboard.element(key).unwrap() {
+            Element::WhiteKey { wide, .. } => m.x + wide.x,
+            Element::BlackKey(r, _) => m.x + r.x,
+        }
+    }
+
+    #[test]
+    fn test_manuals() {
+        let keyboard = KeyboardBuilder::new().set_width(1000).unwrap();
+        let manuals = ManualsBuilder::new(keyboard)
+            .add_manual(36, 96)
+            .unwrap()
+            .add_manual(41, 84)
+            .unwrap()
+            .add_manual(37, 90)
+            .unwrap()
+            .set_spacing(10)
+            .build()
+            .unwrap();
+        assert_eq!(manuals.width, 1000);
+        let upper = manuals.manual(0).unwrap();
+        let height = upper.keyboard.height;
+        assert_eq!(manuals.height, 3 * height + 20);
+        for m in manuals.iter().skip(1) {
+            let (left, right) = (m.keyboard.left_white_key, m.keyboard.right_white_key);
+            for key in left..=right {
+                assert_eq!(wide_x(m, key), wide_x(upper, key));
+            }
+            assert!(m.x + m.keyboard.width <= manuals.width);
+        }
+        assert_eq!(manuals.manual(2).unwrap().y, 2 * height + 20);
+        assert!(!manuals
+            .manual(2)
+            .unwrap()
+            .keyboard
+            .white_keys(true)
+            .is_empty());
+
+        // Hit test in the second manual
+        let m = manuals.manual(1).unwrap();
+        let x = wide_x(m, 60) + 1;
+        let y = m.y + m.keyboard.height * 7 / 8;
+        assert_eq!(manuals.key_at(x, y), Some((1, Hit::Key(60))));
+        assert_eq!(manuals.key_at(x, height + 5), None);
+        assert_eq!(manuals.key_at(2, y).map(|(i, _)| i), None);
+    }
+    #[test]
+    fn test_offset() {
+        let keyboard = KeyboardBuilder::new().set_width(600).unwrap();
+        let manuals = ManualsBuilder::new(keyboard)
+            .add_manual_with_offset(48, 72, 20)
+            .unwrap()
+            .add_manual_with_offset(48, 72, -10)
+            .unwrap()
+            .build()
+            .unwrap();
+        let upper = manuals.manual(0).unwrap();
+        let lower = manuals.manual(1).unwrap();
+        assert_eq!(lower.x, 0);
+        assert_eq!(upper.x, 30);
+        assert_eq!(manuals.width, 630);
+        assert_eq!(wide_x(upper, 60), wide_x(lower, 60) + 30);
+
+        assert_eq!(
+            ManualsBuilder::new(KeyboardBuilder::new()).build().err(),
+            Some(KeyboardError::NoManuals)
+        );
+        assert!(ManualsBuilder::new(KeyboardBuilder::new())
+            .add_manual(60, 59)
+            .is_err());
+    }
+}
@@ -0,0 +1,599 @@
+//! Keyboards with an octave division other than twelve, like 19-EDO, 31-EDO or quarter tones.
+//!
+//! The white/black sequence of one octave is given by a KeyPattern and selected by
+//! KeyboardBuilder::set_key_pattern(). The MIDI key codes are interpreted as steps of
+//! the tuning, so key 0 is the first step of the pattern.
+//!
+//! All white keys have the same width with at most one pixel difference. The enlarged
+//! white keys are listed in the report. The black keys between two white keys are placed
+//! side by side as a group centered on the gap between the white keys. A group has the
+//! width of (k+1)/2 black keys for k black keys, but is narrowed to keep the small parts
+//! of the white keys at least half as wide as the smaller small part of the twelve tone
+//! keyboard.
+//!
+//! Gaps, dot ratio, orientation, mirroring and fitting are applied like for the twelve
+//! tone keyboard. Only the key range has to start and end with a white key.
+use std::convert::TryFrom;
+use std::fmt;
+
+use crate::geometry::KeyTop;
+use crate::{
+    Adjustment, AdjustmentKind, Element, KeyboardBuilder, KeyboardError, LayoutReport, Rectangle,
+    Target, Vertical,
+};
+
+/// The white/black sequence of the keys in one octave.
+///
+/// With the feature `serde` a pattern is serialized as string like for KeyPattern::new().
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "String", into = "String")
+)]
+pub struct KeyPattern {
+    white: Vec<bool>,
+}
+impl Default for KeyPattern {
+    fn default() -> Self {
+        KeyPattern::twelve_tone()
+    }
+}
+impl KeyPattern {
+    /// Create a pattern from a string with one character per step of the octave:
+    /// 'W' for a white key and 'B' for a black key. Whitespace is ignored.
+    /// E.g. the twelve tone keyboard is "WBWBW WBWBWBW".
+    pub fn new(pattern: &str) -> Result<KeyPattern, KeyboardError> {
+        let mut white = vec![];
+        for c in pattern.chars().filter(|c| !c.is_whitespace()) {
+            match c {
+                'W' | 'w' => white.push(true),
+                'B' | 'b' => white.push(false),
+                _ => return Err(KeyboardError::InvalidKeyPattern("only W and B are allowed")),
+            }
+        }
+        if white.is_empty() || white.len() > 127 {
+            return Err(KeyboardError::InvalidKeyPattern(
+                "octave division must be in range 1..=127",
+            ));
+        }
+        if !white.iter().any(|w| *w) {
+            return Err(KeyboardError::InvalidKeyPattern(
+                "at least one white key is needed",
+            ));
+        }
+        Ok(KeyPattern { white })
+    }
+    /// The traditional keyboard
+    pub fn twelve_tone() -> KeyPattern {
+        KeyPattern::new("WBWBW WBWBWBW").unwrap()
+    }
+    /// 19-EDO with sharps and flats as separate black keys and E#/B# between E/F and B/C
+    pub fn edo19() -> KeyPattern {
+        KeyPattern::new("WBBWBBWB WBBWBBWBBWB").unwrap()
+    }
+    /// 24-EDO with the quarter tones as black keys
+    pub fn quarter_tone() -> KeyPattern {
+        KeyPattern::new("WBBBWBBBWB WBBBWBBBWBBBWB").unwrap()
+    }
+    /// 31-EDO with four black keys between whole tones and two between semitones
+    pub fn edo31() -> KeyPattern {
+        KeyPattern::new("WBBBBWBBBBWBB WBBBBWBBBBWBBBBWBB").unwrap()
+    }
+    /// Is true for the pattern of the traditional keyboard
+    pub fn is_twelve_tone(&self) -> bool {
+        *self == KeyPattern::twelve_tone()
+    }
+    /// The number of steps per octave
+    pub fn division(&self) -> u8 {
+        self.white.len() as u8
+    }
+    pub fn is_white(&self, key: u8) -> bool {
+        self.white[key as usize % self.white.len()]
+    }
+    /// The step of a key within its octave.
+    /// For the twelve tone pattern this is the pitch class with 0 for C up to 11 for B.
+    pub fn pitch_class(&self, key: u8) -> u8 {
+        key % self.division()
+    }
+    /// The octave of a key with key code 0 in octave -1 like the MIDI convention.
+    pub fn octave(&self, key: u8) -> i8 {
+        (key / self.division()) as i8 - 1
+    }
+    // The first white key of the first octave
+    pub(crate) fn first_white(&self) -> u8 {
+        self.white.iter().position(|w| *w).unwrap_or(0) as u8
+    }
+    // The number of black keys following resp. preceding a white key
+    fn black_run_after(&self, key: u8) -> u32 {
+        let n = self.white.len();
+        (1..n)
+            .take_while(|i| !self.white[(key as usize + i) % n])
+            .count() as u32
+    }
+    fn black_run_before(&self, key: u8) -> u32 {
+        let n = self.white.len();
+        (1..n)
+            .take_while(|i| !self.white[(key as usize + n - i) % n])
+            .count() as u32
+    }
+    // The longest sequence of black keys between two white keys
+    fn max_black_run(&self) -> u32 {
+        (0..self.division())
+            .filter(|k| self.is_white(*k))
+            .map(|k| self.black_run_after(k))
+            .max()
+            .unwrap_or(0)
+    }
+}
+impl fmt::Display for KeyPattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for w in self.white.iter() {
+            write!(f, "{}", if *w { 'W' } else { 'B' })?;
+        }
+        Ok(())
+    }
+}
+impl TryFrom<String> for KeyPattern {
+    type Error = KeyboardError;
+    fn try_from(pattern: String) -> Result<KeyPattern, KeyboardError> {
+        KeyPattern::new(&pattern)
+    }
+}
+impl From<KeyPattern> for String {
+    fn from(pattern: KeyPattern) -> String {
+        pattern.to_string()
+    }
+}
+
+// A white key needs room for the longest run of black keys with gaps
+// and a small part of at least one pixel.
+pub(crate) fn min_width(kb: &KeyboardBuilder) -> u16 {
+    let nr_of_white_keys = kb.nr_of_white_keys(kb.left_key, kb.right_key);
+    let min_width = nr_of_white_keys * (2 * kb.pattern.max_black_run() + 3) + nr_of_white_keys + 1;
+    min_width.min(65535) as u16
+}
+
+// Calculate the horizontal layout of a key pattern other than twelve tone
+// like KeyboardBuilder::layout(). The key range starts and ends with a white key.
+pub(crate) fn layout(
+    kb: &KeyboardBuilder,
+    vertical: &Vertical,
+) -> Result<(Vec<Element>, u16, LayoutReport), KeyboardError> {
+    let pattern = &kb.pattern;
+    let dims = &kb.dims;
+    let nr_of_white_keys = kb.nr_of_white_keys(kb.left_key, kb.right_key);
+    // The black keys of a group are separated by gaps, so a gap needs one pixel at least
+    let key_gap = vertical.key_gap.max(1);
+    let black_gap = if kb.need_black_gap { key_gap } else { 0 };
+
+    // The white keys share the remaining pixels
+    let remaining = (kb.width as u32).saturating_sub(key_gap * (nr_of_white_keys + 1));
+    let white_x = |i: u32| key_gap * (i + 1) + remaining * i / nr_of_white_keys;
+    let white_key_width = remaining / nr_of_white_keys;
+    let scaled = |v_10um: u32| {
+        let total_10um = nr_of_white_keys as u64 * dims.white_key_wide_width_10um as u64;
+        ((remaining as u64 * v_10um as u64 + total_10um / 2) / total_10um) as u32
+    };
+    let black_key_width = scaled(dims.black_key_width_10um).max(1);
+    let small_min = scaled(
+        dims.white_key_small_width_fb_10um
+            .min(dims.white_key_small_width_ga_10um)
+            / 2,
+    )
+    .max(1);
+
+    // One pixel is reserved for rounding of the group position
+    let group_width = |k: u32| {
+        let min_group = k + (k - 1) * key_gap;
+        let soft_max = (white_key_width + key_gap).saturating_sub(2 * black_gap + small_min);
+        let hard_max = (white_key_width + key_gap).saturating_sub(2 * black_gap + 2);
+        let group = (black_key_width * (k + 1) / 2)
+            .min(soft_max)
+            .max(min_group)
+            .min(hard_max);
+        if group < min_group {
+            Err(KeyboardError::InconsistentLayout("black keys do not fit"))
+        } else {
+            Ok(group)
+        }
+    };
+    // The black keys left of the white key with index i as start and width.
+    // The groups at both ends are outside of the key range and only limit the small parts.
+    let group = |i: u32, k: u32| -> Result<Vec<(i64, i64)>, KeyboardError> {
+        if k == 0 {
+            return Ok(vec![]);
+        }
+        let width = group_width(k)?;
+        let center = (white_x(i) - key_gap + key_gap / 2) as i64;
+        let start = center - (width / 2) as i64;
+        let net = width - (k - 1) * key_gap;
+        Ok((0..k)
+            .map(|j| {
+                let x = start + (j * key_gap + net * j / k) as i64;
+                (x, (net * (j + 1) / k - net * j / k) as i64)
+            })
+            .collect())
+    };
+
+    let key_gap_v = vertical.key_gap_v as u16;
+    let black_gap_v = vertical.black_gap_v as u16;
+    let black_key_height = vertical.black_key_height as u16;
+    let white_key_wide_height = vertical.white_key_wide_height as u16;
+    let top = |start: i64, end: i64| Rectangle {
+        x: start as u16,
+        y: key_gap_v,
+        width: (end - start) as u16,
+        height: black_gap_v + black_key_height,
+    };
+
+    let whites = (kb.left_key..=kb.right_key)
+        .filter(|k| pattern.is_white(*k))
+        .collect::<Vec<_>>();
+    let mut report = LayoutReport {
+        white_key_width: white_key_width as u16,
+        key_gap: key_gap as u16,
+        black_key_width: black_key_width as u16,
+        adjustments: vec![],
+    };
+    let mut elements = vec![];
+    let mut before = group(0, pattern.black_run_before(whites[0]))?;
+    let last = whites.len() - 1;
+    for (i, key) in whites.into_iter().enumerate() {
+        let x = white_x(i as u32);
+        let end = white_x(i as u32 + 1) - key_gap;
+        let after = group(i as u32 + 1, pattern.black_run_after(key))?;
+
+        // The small part is limited by the black keys on both sides
+        let (x, end) = (x as i64, end as i64);
+        let left = before
+            .last()
+            .map(|(start, width)| start + width + black_gap as i64)
+            .unwrap_or(x)
+            .max(x);
+        let right = after
+            .first()
+            .map(|(start, _)| start - black_gap as i64)
+            .unwrap_or(end)
+            .min(end);
+        let blind_left = i == 0 && left > x;
+        let blind_right = i == last && right < end;
+        // Like for the twelve tone layout, a single white key extends its small part
+        let right = if blind_left && blind_right {
+            end
+        } else {
+            right
+        };
+        if right <= left {
+            return Err(KeyboardError::InconsistentLayout(
+                "white key has no small part",
+            ));
+        }
+        let blind = if blind_left {
+            Some(top(x, left))
+        } else if blind_right {
+            Some(top(right, end))
+        } else {
+            None
+        };
+        elements.push(Element::WhiteKey {
+            wide: Rectangle {
+                x: x as u16,
+                y: key_gap_v + black_gap_v + black_key_height,
+                width: (end - x) as u16,
+                height: white_key_wide_height,
+            },
+            small: top(left, right),
+            blind,
+            key,
+        });
+        let enlarged = (end - x) as u16 - report.white_key_width;
+        if enlarged > 0 {
+            report.adjustments.push(Adjustment {
+                kind: AdjustmentKind::WhiteKeys,
+                target: Target::Key(key),
+                pixels: enlarged,
+            });
+        }
+        if i < last {
+            for (j, (start, width)) in after.iter().enumerate() {
+                let rect = Rectangle {
+                    x: *start as u16,
+                    y: key_gap_v,
+                    width: *width as u16,
+                    height: black_key_height,
+                };
+                elements.push(Element::BlackKey(rect, key + 1 + j as u8));
+            }
+        }
+        before = after;
+    }
+
+    Ok((elements, vertical.height() as u16, report))
+}
+
+// The top part of a white key for build2d_f32() with the sizes in mm like for the pixel layout.
+pub(crate) fn top_f32(
+    pattern: &KeyPattern,
+    key: u8,
+    w: f32,
+    g: f32,
+    b: f32,
+    black_gap: f32,
+    small_min: f32,
+) -> KeyTop {
+    let group_width = |k: u32| {
+        (b * (k + 1) as f32 / 2.0)
+            .min(w + g - 2.0 * black_gap - small_min)
+            .max((k - 1) as f32 * g)
+    };
+    let small_start = match pattern.black_run_before(key) {
+        0 => 0.0,
+        k => ((group_width(k) - g) / 2.0 + black_gap).max(0.0),
+    };
+    let mut small_end = w;
+    let mut blacks = vec![];
+    let k = pattern.black_run_after(key);
+    if k > 0 {
+        let width = group_width(k);
+        let start = w + (g - width) / 2.0;
+        let black_width = (width - (k - 1) as f32 * g) / k as f32;
+        small_end = (start - black_gap).min(w);
+        blacks = (0..k)
+            .map(|j| (start + j as f32 * (black_width + g), black_width))
+            .collect();
+    }
+    KeyTop {
+        small_start,
+        small_width: (small_end - small_start).max(0.0),
+        blacks,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        AdjustmentKind, Element, FitStrategy, Hit, KeyPattern, Keyboard2d, KeyboardBuilder,
+        KeyboardError, Orientation,
+    };
+
+    #[test]
+    fn test_patterns() {
+        assert_eq!(KeyPattern::twelve_tone().division(), 12);
+        assert_eq!(KeyPattern::edo19().division(), 19);
+        assert_eq!(KeyPattern::quarter_tone().division(), 24);
+        assert_eq!(KeyPattern::edo31().division(), 31);
+        for p in [KeyPattern::edo19(), KeyPattern::edo31()] {
+            let whites = (0..p.division()).filter(|k| p.is_white(*k)).count();
+            assert_eq!(whites, 7);
+        }
+        assert_eq!(KeyPattern::edo31().max_black_run(), 4);
+        assert_eq!(KeyPattern::twelve_tone().max_black_run(), 1);
+        assert_eq!(KeyPattern::edo19().black_run_before(3), 2);
+        assert_eq!(KeyPattern::edo19().black_run_after(6), 1);
+        assert!(KeyPattern::new("WBX").is_err());
+        assert!(KeyPattern::new("BB").is_err());
+        assert!(KeyPattern::new("").is_err());
+        assert!(KeyPattern::default().is_twelve_tone());
+        assert!(!KeyPattern::edo19().is_twelve_tone());
+        assert_eq!(KeyPattern::twelve_tone().to_string(), "WBWBWWBWBWBW");
+        let edo31 = KeyPattern::edo31();
+        assert_eq!(edo31.pitch_class(62), 0);
+        assert_eq!(edo31.octave(62), 1);
+        assert_eq!(KeyPattern::twelve_tone().octave(60), 4);
+    }
+    #[test]
+    fn test_microtonal() {
+        for pattern in [
+            KeyPattern::twelve_tone(),
+            KeyPattern::edo19(),
+            KeyPattern::quarter_tone(),
+            KeyPattern::edo31(),
+        ] {
+            let builder = KeyboardBuilder::new()
+                .set_key_pattern(pattern.clone())
+                .unwrap();
+            let division = pattern.division();
+            for width in 100..1200 {
+                let keyboard = match builder.clone().set_width(width) {
+                    Ok(b) => b.try_build2d().unwrap(),
+                    Err(_) => continue,
+                };
+                assert_eq!(keyboard.iter().count(), 2 * division as usize + 1);
+                assert_eq!(keyboard.key_pattern(), &pattern);
+                // The outter gaps are equal and the white keys differ at most by one pixel.
+                // The twelve tone pattern uses the layout of the KeyboardBuilder.
+                let wides = keyboard
+                    .iter()
+                    .filter_map(|el| match el {
+                        Element::WhiteKey { wide, .. } => Some(wide.clone()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                let last = wides.last().unwrap();
+                let min = wides.iter().map(|r| r.width).min().unwrap();
+                let max = wides.iter().map(|r| r.width).max().unwrap();
+                if !pattern.is_twelve_tone() {
+                    assert_eq!(wides[0].x, width - last.x - last.width);
+                    assert!(max - min <= 1);
+                }
+                for el in keyboard.iter() {
+                    assert_eq!(el.is_white(), pattern.is_white(el.key()));
+                    let outline = keyboard.outline(el.key()).unwrap();
+                    assert!(outline.len() >= 4);
+                }
+                // Every pixel hits the key containing it
+                if width != 400 {
+                    continue;
+                }
+                for y in 0..keyboard.height {
+                    for x in 0..width {
+                        let owners = keyboard.iter().filter(|el| el.contains(x, y)).count();
+                        assert!(owners <= 1);
+                        match keyboard.key_at(x, y) {
+                            Hit::Key(key) => {
+                                assert!(keyboard.element(key).unwrap().contains(x, y))
+                            }
+                            _ => assert_eq!(owners, 0),
+                        }
+                    }
+                }
+            }
+        }
+        let builder = KeyboardBuilder::new()
+            .set_key_pattern(KeyPattern::edo19())
+            .unwrap();
+        assert!(builder.clone().set_most_left_right_keys(0, 19).is_ok());
+        assert_eq!(
+            builder.clone().set_most_left_right_keys(1, 19).err(),
+            Some(KeyboardError::NotAWhiteKey(1))
+        );
+        assert!(builder.clone().set_width(50).is_err());
+        // A key range set before the pattern is kept, if it is valid for the pattern
+        let keyboard = KeyboardBuilder::new()
+            .set_most_left_right_keys(19, 57)
+            .unwrap()
+            .set_key_pattern(KeyPattern::edo19())
+            .unwrap()
+            .build2d();
+        assert_eq!(
+            (keyboard.left_white_key, keyboard.right_white_key),
+            (19, 57)
+        );
+        assert_eq!(
+            KeyboardBuilder::new()
+                .set_most_left_right_keys(20, 57)
+                .unwrap()
+                .set_key_pattern(KeyPattern::edo19())
+                .err(),
+            Some(KeyboardError::NotAWhiteKey(20))
+        );
+        assert_eq!(
+            KeyboardBuilder::new()
+                .standard_piano(88)
+                .unwrap()
+                .set_key_pattern(KeyPattern::edo19())
+                .err(),
+            Some(KeyboardError::NotAWhiteKey(21))
+        );
+        // A standard piano is always twelve tone
+        let piano = builder.standard_piano(88).unwrap();
+        assert_eq!(piano.build2d().iter().count(), 88);
+    }
+    #[test]
+    fn test_microtonal_report() {
+        let builder = KeyboardBuilder::new()
+            .set_key_pattern(KeyPattern::edo31())
+            .unwrap();
+        for width in 500..700 {
+            let keyboard = builder.clone().set_width(width).unwrap().build2d();
+            let report = keyboard.report();
+            let enlarged: u16 = report
+                .adjustments_of(AdjustmentKind::WhiteKeys)
+                .map(|a| a.pixels)
+                .sum();
+            assert_eq!(
+                15 * report.white_key_width + 16 * report.key_gap + enlarged,
+                width
+            );
+            assert_eq!(keyboard.is_perfect(), enlarged == 0);
+            assert!(report.black_key_width > 0);
+        }
+        assert!(!builder.perfect_widths(500..=700).is_empty());
+    }
+    #[test]
+    fn test_microtonal_options() {
+        let builder = KeyboardBuilder::new()
+            .set_key_pattern(KeyPattern::quarter_tone())
+            .unwrap()
+            .set_width(800)
+            .unwrap();
+        let horizontal = builder.clone().build2d();
+
+        // The orientation and mirroring are applied like for the twelve tone keyboard
+        let vertical = builder
+            .clone()
+            .set_orientation(Orientation::VerticalLowAtTop)
+            .set_mirror(true, false)
+            .build2d();
+        assert_eq!(
+            (vertical.width, vertical.height),
+            (horizontal.height, horizontal.width)
+        );
+        assert_eq!(vertical.orientation(), Orientation::VerticalLowAtTop);
+        let area = |keyboard: &Keyboard2d, key: u8| {
+            let el = keyboard.element(key).unwrap();
+            el.rects()
+                .iter()
+                .map(|r| r.width as u32 * r.height as u32)
+                .sum::<u32>()
+        };
+        for el in horizontal.iter() {
+            assert_eq!(area(&vertical, el.key()), area(&horizontal, el.key()));
+        }
+
+        let tall = builder.clone().set_dot_ratio(1, 2).unwrap().build2d();
+        assert!(tall.height * 2 <= horizontal.height + 4);
+
+        // Without the gaps next to the black keys the small parts touch them
+        let no_gap = builder.clone().white_black_gap_present(false).build2d();
+        let touching = |keyboard: &Keyboard2d| {
+            let small = match keyboard.element(0).unwrap() {
+                Element::WhiteKey { small, .. } => small.x + small.width,
+                _ => unreachable!(),
+            };
+            match keyboard.element(1).unwrap() {
+                Element::BlackKey(r, _) => r.x == small,
+                _ => unreachable!(),
+            }
+        };
+        assert!(touching(&no_gap));
+        assert!(!touching(&horizontal));
+
+        let fitted = builder
+            .clone()
+            .fit_into(800, 60, FitStrategy::ShrinkWidth)
+            .unwrap()
+            .build2d();
+        assert!(fitted.height <= 60 && fitted.width < 800);
+        assert_eq!(fitted.key_pattern(), &KeyPattern::quarter_tone());
+    }
+    #[test]
+    fn test_microtonal_blinds() {
+        // The black keys outside of the range are not visible, but limit the small parts
+        let keyboard = KeyboardBuilder::new()
+            .set_key_pattern(KeyPattern::edo19())
+            .unwrap()
+            .set_most_left_right_keys(3, 6)
+            .unwrap()
+            .set_width(200)
+            .unwrap()
+            .build2d();
+        assert_eq!(keyboard.iter().count(), 4);
+        for key in [3, 6].iter() {
+            match keyboard.element(*key).unwrap() {
+                Element::WhiteKey { blind, .. } => assert!(blind.is_some()),
+                _ => unreachable!(),
+            }
+        }
+    }
+    #[test]
+    fn test_microtonal_f32() {
+        let builder = KeyboardBuilder::new()
+            .set_key_pattern(KeyPattern::edo31())
+            .unwrap()
+            .set_width(2000)
+            .unwrap();
+        let keyboard = builder.clone().build2d();
+        let keyboard_f32 = builder.build2d_f32();
+        assert_eq!(keyboard.iter().count(), keyboard_f32.iter().count());
+        for (el, el_f32) in keyboard.iter().zip(keyboard_f32.iter()) {
+            assert_eq!(el.key(), el_f32.key());
+            assert_eq!(el.is_white(), el_f32.is_white());
+            for (r, r_f32) in el.rects().iter().zip(el_f32.rects()) {
+                assert!((r.x as f32 - r_f32.x).abs() < 4.0);
+                assert!((r.width as f32 - r_f32.width).abs() < 4.0);
+            }
+        }
+    }
+}
@@ -0,0 +1,155 @@
+//! Closed polygon outlines of the keys.
+//!
+//! The outline is derived from the rectangles of a key in the canonical layout.
+//! There the small and blind parts of a white key sit on top of the wide part,
+//! so the outline is a skyline above the wide part.
+use crate::{Element, Keyboard2d, Rectangle};
+
+impl Keyboard2d {
+    /// The outline of a key as closed polygon with the vertices in clockwise order
+    /// as seen on the screen (y pointing down). The first vertex is not repeated at the end.
+    ///
+    /// The vertices are at the corners of the pixels. So a rectangle covering
+    /// the pixels x..x+width has its vertices at x and x+width.
+    /// The blind part of a white key is included like in Element::contains().
+    ///
+    /// Returns None, if the key is not part of the keyboard.
+    pub fn outline(&self, key: u8) -> Option<Vec<(u16, u16)>> {
+        let el = self.element(key)?;
+        let canonical = |r: &Rectangle| self.transform.inverse_rect(r);
+        let mut points = match el {
+            Element::BlackKey(r, _) => {
+                let r = canonical(r);
+                vec![
+                    (r.x, r.y),
+                    (r.x + r.width, r.y),
+                    (r.x + r.width, r.y + r.height),
+                    (r.x, r.y + r.height),
+                ]
+            }
+            Element::WhiteKey {
+                wide, small, blind, ..
+            } => {
+                let wide = canonical(wide);
+                let mut tops = vec![canonical(small)];
+                tops.extend(blind.iter().map(canonical));
+                skyline(&wide, tops)
+            }
+        };
+        points = points
+            .iter()
+            .map(|(x, y)| self.transform.point(*x, *y))
+            .collect();
+        // Mirroring and some rotations reverse the order of the vertices
+        if signed_area(&points) < 0 {
+            points.reverse();
+        }
+        Some(points)
+    }
+}
+
+// Outline of the wide part with the top parts on top of it in clockwise order
+fn skyline(wide: &Rectangle, mut tops: Vec<Rectangle>) -> Vec<(u16, u16)> {
+    tops.retain(|r| r.width > 0 && r.height > 0);
+    tops.sort_by_key(|r| r.x);
+    // Adjacent parts are merged, so no vertices are placed along a straight edge
+    let mut spans: Vec<(u16, u16, u16)> = vec![];
+    for r in tops.iter() {
+        match spans.last_mut() {
+            Some(last) if last.1 >= r.x => last.1 = last.1.max(r.x + r.width),
+            _ => spans.push((r.x, r.x + r.width, r.y)),
+        }
+    }
+
+    let left = wide.x;
+    let right = wide.x + wide.width;
+    let base = wide.y;
+    let bottom = wide.y + wide.height;
+    let mut points = vec![];
+    for (start, end, top) in spans.iter() {
+        if *start > left {
+            points.push((*start, base));
+        }
+        points.push((*start, *top));
+        points.push((*end, *top));
+        if *end < right {
+            points.push((*end, base));
+        }
+    }
+    // Corners on the wide part are only needed, if no top part continues the edge
+    if spans.last().map(|s| s.1 < right).unwrap_or(true) {
+        points.push((right, base));
+    }
+    points.push((right, bottom));
+    points.push((left, bottom));
+    if spans.first().map(|s| s.0 > left).unwrap_or(true) {
+        points.push((left, base));
+    }
+    points
+}
+
+// Twice the area of the polygon. Positive for clockwise order with y pointing down.
+fn signed_area(points: &[(u16, u16)]) -> i64 {
+    let n = points.len();
+    (0..n)
+        .map(|i| {
+            let (x0, y0) = points[i];
+            let (x1, y1) = points[(i + 1) % n];
+            x0 as i64 * y1 as i64 - x1 as i64 * y0 as i64
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::outline::signed_area;
+    use crate::{KeyboardBuilder, Orientation};
+
+    #[test]
+    fn test_outline() {
+        for orientation in [
+            Orientation::Horizontal,
+            Orientation::VerticalLowAtBottom,
+            Orientation::VerticalLowAtTop,
+        ] {
+            for (h, v) in [(false, false), (true, false), (false, true), (true, true)] {
+                for (left, right) in [(21, 108), (49, 70), (50, 50)] {
+                    let keyboard = KeyboardBuilder::new()
+                        .set_most_left_right_keys(left, right)
+                        .unwrap()
+                        .set_width(900)
+                        .unwrap()
+                        .set_orientation(orientation)
+                        .set_mirror(h, v)
+                        .build2d();
+                    for el in keyboard.iter() {
+                        let outline = keyboard.outline(el.key()).unwrap();
+                        let area: u32 = el
+                            .rects()
+                            .iter()
+                            .map(|r| r.width as u32 * r.height as u32)
+                            .sum();
+                        assert_eq!(signed_area(&outline), 2 * area as i64);
+                        // Every edge is either horizontal or vertical
+                        for (i, (x, y)) in outline.iter().enumerate() {
+                            let (x1, y1) = outline[(i + 1) % outline.len()];
+                            assert!((*x == x1) != (*y == y1));
+                        }
+                    }
+                    assert!(keyboard.outline(left - 1).is_none());
+                }
+            }
+        }
+        let keyboard = KeyboardBuilder::new()
+            .set_most_left_right_white_keys(48, 71)
+            .unwrap()
+            .set_width(400)
+            .unwrap()
+            .build2d();
+        // Inner keys: black keys are rectangles, c/e are L-shaped, d is a T
+        assert_eq!(keyboard.outline(61).unwrap().len(), 4);
+        assert_eq!(keyboard.outline(60).unwrap().len(), 6);
+        assert_eq!(keyboard.outline(62).unwrap().len(), 8);
+        assert_eq!(keyboard.outline(64).unwrap().len(), 6);
+    }
+}
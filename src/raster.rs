@@ -0,0 +1,215 @@
+//! Software rendering of a Keyboard2d into an RGBA pixel buffer.
+//!
+//! The buffer is provided by the caller with 4 bytes per pixel in the order R, G, B, A.
+//! The rows may be padded, so the stride in bytes can be larger than 4 * width.
+//! Padding bytes are not touched. All rectangles are filled row by row as spans.
+use crate::{Element, Keyboard2d, KeyboardError, Rectangle};
+
+/// The colors for painting a keyboard as RGBA.
+#[derive(Clone, Debug)]
+pub struct RgbaStyle {
+    background: [u8; 4],
+    white: [u8; 4],
+    black: [u8; 4],
+    key_colors: [Option<[u8; 4]>; 128],
+}
+impl Default for RgbaStyle {
+    fn default() -> Self {
+        RgbaStyle::new()
+    }
+}
+impl RgbaStyle {
+    /// Gray background for the gaps, white and black keys like in the example make_png.
+    pub fn new() -> RgbaStyle {
+        RgbaStyle {
+            background: [150, 150, 150, 255],
+            white: [255, 255, 255, 255],
+            black: [0, 0, 0, 255],
+            key_colors: [None; 128],
+        }
+    }
+    /// The color of the gaps and of the area outside of the keys.
+    pub fn set_background(mut self, color: [u8; 4]) -> RgbaStyle {
+        self.background = color;
+        self
+    }
+    pub fn set_white(mut self, color: [u8; 4]) -> RgbaStyle {
+        self.white = color;
+        self
+    }
+    pub fn set_black(mut self, color: [u8; 4]) -> RgbaStyle {
+        self.black = color;
+        self
+    }
+    /// Override the color of one key, e.g. to show a pressed key.
+    /// Keys out of the MIDI range are ignored.
+    pub fn set_key_color(mut self, key: u8, color: [u8; 4]) -> RgbaStyle {
+        if let Some(c) = self.key_colors.get_mut(key as usize) {
+            *c = Some(color);
+        }
+        self
+    }
+    /// Remove all overrides of key colors.
+    pub fn clear_key_colors(mut self) -> RgbaStyle {
+        self.key_colors = [None; 128];
+        self
+    }
+    /// The color of a key considering the overrides.
+    pub fn key_color(&self, el: &Element) -> [u8; 4] {
+        let default = if el.is_white() {
+            self.white
+        } else {
+            self.black
+        };
+        self.key_colors
+            .get(el.key() as usize)
+            .and_then(|c| *c)
+            .unwrap_or(default)
+    }
+}
+
+impl Keyboard2d {
+    /// Paint the keyboard into the buffer with the given stride in bytes.
+    ///
+    /// The buffer needs at least stride * (height - 1) + 4 * width bytes.
+    pub fn paint_rgba(
+        &self,
+        buffer: &mut [u8],
+        stride: usize,
+        style: &RgbaStyle,
+    ) -> Result<(), KeyboardError> {
+        let row_len = 4 * self.width as usize;
+        if stride < row_len {
+            return Err(KeyboardError::InvalidStride {
+                stride,
+                min_stride: row_len,
+            });
+        }
+        let required = stride * (self.height as usize).saturating_sub(1) + row_len;
+        if buffer.len() < required {
+            return Err(KeyboardError::BufferTooSmall {
+                len: buffer.len(),
+                required,
+            });
+        }
+
+        let mut fill = |r: &Rectangle, color: [u8; 4]| {
+            for y in r.y as usize..(r.y + r.height) as usize {
+                let start = y * stride + 4 * r.x as usize;
+                let span = &mut buffer[start..start + 4 * r.width as usize];
+                for pixel in span.chunks_exact_mut(4) {
+                    pixel.copy_from_slice(&color);
+                }
+            }
+        };
+        fill(
+            &Rectangle {
+                x: 0,
+                y: 0,
+                width: self.width,
+                height: self.height,
+            },
+            style.background,
+        );
+        for el in self.elements.iter() {
+            let color = style.key_color(el);
+            for r in el.rects() {
+                fill(r, color);
+            }
+        }
+        for r in self.blinds.iter() {
+            fill(r, style.white);
+        }
+        Ok(())
+    }
+    /// The keyboard as RGBA image without padding, so the stride is 4 * width.
+    pub fn to_rgba(&self, style: &RgbaStyle) -> Vec<u8> {
+        let stride = 4 * self.width as usize;
+        let mut buffer = vec![0; stride * self.height as usize];
+        // The buffer fits by construction
+        let _ = self.paint_rgba(&mut buffer, stride, style);
+        buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Hit, KeyboardBuilder, KeyboardError, Orientation, RgbaStyle};
+
+    #[test]
+    fn test_paint_rgba() {
+        let keyboard = KeyboardBuilder::new()
+            .set_most_left_right_keys(46, 75)
+            .unwrap()
+            .set_width(317)
+            .unwrap()
+            .set_orientation(Orientation::VerticalLowAtTop)
+            .build2d();
+        let (width, height) = (keyboard.width as usize, keyboard.height as usize);
+        let background = [1, 2, 3, 4];
+        let style = RgbaStyle::new().set_background(background);
+
+        // Reference by per pixel indexing as in the example make_png
+        let mut expected = vec![0; 4 * width * height];
+        for i in 0..width * height {
+            expected[4 * i..4 * i + 4].copy_from_slice(&background);
+        }
+        for (color, rects) in [
+            ([255, 255, 255, 255], keyboard.white_keys(true)),
+            ([0, 0, 0, 255], keyboard.black_keys()),
+        ] {
+            for r in rects {
+                for y in r.y as usize..(r.y + r.height) as usize {
+                    for x in r.x as usize..(r.x + r.width) as usize {
+                        let i = 4 * (y * width + x);
+                        expected[i..i + 4].copy_from_slice(&color);
+                    }
+                }
+            }
+        }
+        assert_eq!(keyboard.to_rgba(&style), expected);
+
+        // Padded rows and key overrides
+        let stride = 4 * width + 12;
+        let mut buffer = vec![7; stride * height];
+        let pressed = [255, 0, 0, 255];
+        let style = style.set_key_color(60, pressed).set_key_color(61, pressed);
+        keyboard.paint_rgba(&mut buffer, stride, &style).unwrap();
+        for y in 0..height {
+            let row = &buffer[y * stride..(y + 1) * stride];
+            assert!(row[4 * width..].iter().all(|b| *b == 7));
+            for x in 0..width {
+                let pixel = &row[4 * x..4 * x + 4];
+                match keyboard.key_at(x as u16, y as u16) {
+                    Hit::Key(60) | Hit::Key(61) => assert_eq!(pixel, pressed),
+                    _ => assert_eq!(pixel, &expected[4 * (y * width + x)..][..4]),
+                }
+            }
+        }
+        let style = style.clear_key_colors();
+        keyboard.paint_rgba(&mut buffer, stride, &style).unwrap();
+        for y in 0..height {
+            assert_eq!(
+                buffer[y * stride..y * stride + 4 * width],
+                expected[4 * y * width..4 * (y + 1) * width]
+            );
+        }
+
+        // The last row does not need the padding
+        let required = stride * (height - 1) + 4 * width;
+        assert!(keyboard
+            .paint_rgba(&mut buffer[..required], stride, &style)
+            .is_ok());
+        assert_eq!(
+            keyboard.paint_rgba(&mut buffer[..required - 1], stride, &style),
+            Err(KeyboardError::BufferTooSmall {
+                len: required - 1,
+                required
+            })
+        );
+        assert!(matches!(
+            keyboard.paint_rgba(&mut buffer, 4 * width - 4, &style),
+            Err(KeyboardError::InvalidStride { .. })
+        ));
+    }
+}
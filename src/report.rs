@@ -0,0 +1,168 @@
+//! Report of the compromises applied by the layout calculation.
+//!
+//! In order to fill the requested width pixel exactly, gaps and keys are enlarged
+//! by the layout calculation. The report lists every enlarged element with the
+//! enlargement in pixels compared to the common white key width resp. key gap.
+use crate::Keyboard2d;
+
+/// The kind of an adjustment as applied by the layout calculation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AdjustmentKind {
+    /// The gaps at the left and/or right end of the keyboard are enlarged
+    OutterGaps,
+    /// The gaps between b and c are enlarged
+    GapsBC,
+    /// The gaps between e and f are enlarged
+    GapsEF,
+    /// All keys d are enlarged
+    KeysD,
+    /// Every second key d is enlarged
+    AlternatingKeysD,
+    /// The most left and/or right white key is enlarged
+    EndKeys,
+    /// The keys c, d and e are enlarged
+    KeysCDE,
+    /// The keys f, g, a and b are enlarged
+    KeysFGAB,
+    /// The black key g# is wider than f# and a#
+    BlackKeysGs,
+    /// A white key of a key pattern other than twelve tone is enlarged
+    WhiteKeys,
+}
+impl AdjustmentKind {
+    /// Enlarged gaps are not considered as compromise. All other adjustments
+    /// make the keyboard not perfect.
+    pub fn is_compromise(&self) -> bool {
+        !matches!(
+            self,
+            AdjustmentKind::OutterGaps | AdjustmentKind::GapsBC | AdjustmentKind::GapsEF
+        )
+    }
+}
+
+/// The element touched by an adjustment.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Target {
+    /// The key with this MIDI key code
+    Key(u8),
+    /// The gap left of the white key with this MIDI key code
+    GapLeftOf(u8),
+    /// The gap right of the white key with this MIDI key code.
+    /// Only used for the outter gap at the right end.
+    GapRightOf(u8),
+}
+impl Target {
+    /// The MIDI key code of the key resp. the white key next to the gap.
+    pub fn key(&self) -> u8 {
+        match self {
+            Target::Key(key) | Target::GapLeftOf(key) | Target::GapRightOf(key) => *key,
+        }
+    }
+}
+
+/// One enlarged key or gap.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Adjustment {
+    pub kind: AdjustmentKind,
+    pub target: Target,
+    /// The enlargement in pixels
+    pub pixels: u16,
+}
+
+/// The report as returned by Keyboard2d::report().
+///
+/// If the keyboard starts or ends with a black key, then the neighbouring white keys
+/// outside of the key range may be listed, too. Their visible parts are the blinds.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LayoutReport {
+    /// The width of the wide part of a not enlarged white key
+    pub white_key_width: u16,
+    /// The width of a not enlarged gap between white keys
+    pub key_gap: u16,
+    /// The width of a not enlarged black key
+    pub black_key_width: u16,
+    pub(crate) adjustments: Vec<Adjustment>,
+}
+impl LayoutReport {
+    /// All adjustments of white keys and gaps from low to high keys,
+    /// followed by the adjustments of the black keys.
+    pub fn adjustments(&self) -> &[Adjustment] {
+        &self.adjustments
+    }
+    /// The adjustments of the given kind.
+    pub fn adjustments_of(&self, kind: AdjustmentKind) -> impl Iterator<Item = &Adjustment> {
+        self.adjustments.iter().filter(move |a| a.kind == kind)
+    }
+    /// Same as Keyboard2d::is_perfect()
+    pub fn is_perfect(&self) -> bool {
+        !self.adjustments.iter().any(|a| a.kind.is_compromise())
+    }
+}
+
+impl Keyboard2d {
+    /// The compromises applied to reach the requested width.
+    pub fn report(&self) -> &LayoutReport {
+        &self.report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::report::{AdjustmentKind, Target};
+    use crate::{Element, KeyboardBuilder};
+
+    #[test]
+    fn test_report() {
+        let builder = KeyboardBuilder::new()
+            .set_most_left_right_keys(24, 35)
+            .unwrap();
+        let keyboard = builder.clone().set_width(811).unwrap().build2d();
+        assert!(keyboard.is_perfect());
+        assert!(keyboard.report().is_perfect());
+        assert!(keyboard.report().white_key_width > 0);
+
+        for width in 700..=900 {
+            let keyboard = builder.clone().set_width(width).unwrap().build2d();
+            let report = keyboard.report();
+            assert_eq!(keyboard.is_perfect(), report.is_perfect());
+            assert!(report.adjustments().iter().all(|a| a.pixels > 0));
+
+            // The wide parts and gaps add up to the width
+            let keys: u16 = keyboard
+                .iter()
+                .filter_map(|el| match el {
+                    Element::WhiteKey { wide, .. } => Some(wide.width),
+                    _ => None,
+                })
+                .sum();
+            let mut expected = 7 * report.white_key_width + 8 * report.key_gap;
+            for a in report.adjustments() {
+                if a.kind != AdjustmentKind::BlackKeysGs {
+                    expected += a.pixels;
+                }
+            }
+            assert_eq!(expected, width);
+            let gaps = width - keys;
+            let enlarged_gaps: u16 = report
+                .adjustments()
+                .iter()
+                .filter(|a| !matches!(a.target, Target::Key(_)))
+                .map(|a| a.pixels)
+                .sum();
+            assert_eq!(gaps, 8 * report.key_gap + enlarged_gaps);
+
+            for a in report.adjustments_of(AdjustmentKind::BlackKeysGs) {
+                assert_eq!(a.target, Target::Key(32));
+                let width_of = |key| match keyboard.element(key).unwrap() {
+                    Element::BlackKey(r, _) => r.width,
+                    _ => unreachable!(),
+                };
+                assert_eq!(width_of(32), width_of(30) + a.pixels);
+            }
+        }
+    }
+}
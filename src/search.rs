@@ -0,0 +1,64 @@
+//! Search for widths, which allow a perfect layout.
+//!
+//! A layout is perfect, if no compromises on width/gaps are needed.
+//! See Keyboard2d::is_perfect().
+use std::ops::RangeInclusive;
+
+use crate::KeyboardBuilder;
+
+impl KeyboardBuilder {
+    /// Returns true, if the layout for the given width is perfect.
+    /// Widths not accepted by set_width() are never perfect.
+    pub fn is_perfect_width(&self, width: u16) -> bool {
+        let kb = match self.clone().set_width(width) {
+            Ok(kb) => kb,
+            Err(_) => return false,
+        };
+        match kb.solver().and_then(|(solver, _)| solver.layout()) {
+            Ok((_, _, report)) => report.is_perfect(),
+            Err(_) => false,
+        }
+    }
+    /// List all widths in the given range with a perfect layout for the current key range
+    /// and options.
+    pub fn perfect_widths(&self, widths: RangeInclusive<u16>) -> Vec<u16> {
+        widths.filter(|w| self.is_perfect_width(*w)).collect()
+    }
+    /// Find the perfect width closest to the given width within max_distance.
+    /// If two widths are equally close, the smaller one is returned.
+    pub fn nearest_perfect_width(&self, width: u16, max_distance: u16) -> Option<u16> {
+        (0..=max_distance).find_map(|distance| {
+            let smaller = width.checked_sub(distance);
+            let bigger = width.checked_add(distance);
+            smaller
+                .into_iter()
+                .chain(bigger)
+                .find(|w| self.is_perfect_width(*w))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::KeyboardBuilder;
+
+    #[test]
+    fn test_perfect_widths() {
+        // The example from the documentation
+        let builder = KeyboardBuilder::new()
+            .set_most_left_right_keys(24, 35)
+            .unwrap();
+        assert!(builder.is_perfect_width(811));
+        let widths = builder.perfect_widths(700..=900);
+        assert!(widths.contains(&811));
+        for width in 700..=900 {
+            let keyboard = builder.clone().set_width(width).unwrap().build2d();
+            assert_eq!(keyboard.is_perfect(), widths.contains(&width));
+        }
+        assert_eq!(builder.nearest_perfect_width(811, 0), Some(811));
+        let nearest = builder.nearest_perfect_width(812, 100).unwrap();
+        let distance = (nearest as i32 - 812).abs();
+        assert!(widths.iter().all(|w| (*w as i32 - 812).abs() >= distance));
+        assert!(!builder.is_perfect_width(1));
+    }
+}
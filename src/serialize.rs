@@ -0,0 +1,223 @@
+//! Serialization of a Keyboard2d with serde.
+//!
+//! The keyboard is serialized as a map with the public fields, the perfection flag
+//! and all elements in the output coordinates. Only the perfection flag is derived
+//! from the report, so it is ignored on deserialization.
+//! The key pattern is optional and defaults to twelve tone.
+//! The deserialized keyboard is checked for consistency of key range, elements and size.
+//! All rectangles have to be within the size of the keyboard.
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::transform::Transform;
+use crate::{Element, Fit, KeyPattern, Keyboard2d, LayoutReport, Rectangle};
+
+#[derive(Serialize)]
+struct Keyboard2dRef<'a> {
+    left_white_key: u8,
+    right_white_key: u8,
+    width: u16,
+    height: u16,
+    perfect: bool,
+    elements: &'a [Element],
+    blinds: &'a [Rectangle],
+    report: &'a LayoutReport,
+    transform: &'a Transform,
+    fit: Option<Fit>,
+    pattern: &'a KeyPattern,
+}
+
+#[derive(Deserialize)]
+struct Keyboard2dData {
+    left_white_key: u8,
+    right_white_key: u8,
+    width: u16,
+    height: u16,
+    elements: Vec<Element>,
+    #[serde(default)]
+    blinds: Vec<Rectangle>,
+    report: LayoutReport,
+    transform: Transform,
+    #[serde(default)]
+    fit: Option<Fit>,
+    #[serde(default)]
+    pattern: KeyPattern,
+}
+
+impl Serialize for Keyboard2d {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Keyboard2dRef {
+            left_white_key: self.left_white_key,
+            right_white_key: self.right_white_key,
+            width: self.width,
+            height: self.height,
+            perfect: self.is_perfect(),
+            elements: &self.elements,
+            blinds: &self.blinds,
+            report: &self.report,
+            transform: &self.transform,
+            fit: self.fit,
+            pattern: &self.pattern,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Keyboard2d {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Keyboard2d, D::Error> {
+        let data = Keyboard2dData::deserialize(deserializer)?;
+        if data.left_white_key > data.right_white_key || data.right_white_key > 127 {
+            return Err(D::Error::custom(format!(
+                "invalid key range {}..={}",
+                data.left_white_key, data.right_white_key
+            )));
+        }
+        // element() relies on one element per key in ascending order
+        let keys_ok = data.elements.len()
+            == (data.right_white_key - data.left_white_key) as usize + 1
+            && data
+                .elements
+                .iter()
+                .zip(data.left_white_key..=data.right_white_key)
+                .all(|(el, key)| el.key() == key && el.is_white() == data.pattern.is_white(key));
+        if !keys_ok {
+            return Err(D::Error::custom("elements do not match the key range"));
+        }
+        if data.transform.size() != (data.width, data.height) {
+            return Err(D::Error::custom(format!(
+                "size {}x{} does not match the transform",
+                data.width, data.height
+            )));
+        }
+        // The rectangles are used for indexing, e.g. by paint_rgba()
+        let inside = |r: &Rectangle| {
+            r.x as u32 + r.width as u32 <= data.width as u32
+                && r.y as u32 + r.height as u32 <= data.height as u32
+        };
+        let outside = data
+            .elements
+            .iter()
+            .flat_map(|el| el.rects())
+            .chain(data.blinds.iter())
+            .find(|r| !inside(r));
+        if let Some(r) = outside {
+            return Err(D::Error::custom(format!(
+                "rectangle {}x{} at {}/{} exceeds the size {}x{}",
+                r.width, r.height, r.x, r.y, data.width, data.height
+            )));
+        }
+        Ok(Keyboard2d {
+            left_white_key: data.left_white_key,
+            right_white_key: data.right_white_key,
+            width: data.width,
+            height: data.height,
+            report: data.report,
+            elements: data.elements,
+            blinds: data.blinds,
+            transform: data.transform,
+            fit: data.fit,
+            pattern: data.pattern,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{FitStrategy, Hit, KeyPattern, Keyboard2d, KeyboardBuilder, Orientation};
+
+    fn round_trip(keyboard: &Keyboard2d) -> Keyboard2d {
+        let json = serde_json::to_string(keyboard).unwrap();
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let keyboard = KeyboardBuilder::new()
+            .set_most_left_right_keys(49, 78)
+            .unwrap()
+            .set_width(531)
+            .unwrap()
+            .set_orientation(Orientation::VerticalLowAtBottom)
+            .set_mirror(true, false)
+            .build2d();
+        let json = serde_json::to_value(&keyboard).unwrap();
+        assert_eq!(json["left_white_key"], 49);
+        assert_eq!(json["width"], keyboard.width);
+        assert_eq!(json["perfect"], keyboard.is_perfect());
+        assert_eq!(json["elements"].as_array().unwrap().len(), 30);
+
+        let copy = round_trip(&keyboard);
+        assert_eq!(copy.left_white_key, keyboard.left_white_key);
+        assert_eq!(copy.right_white_key, keyboard.right_white_key);
+        assert_eq!((copy.width, copy.height), (keyboard.width, keyboard.height));
+        assert_eq!(copy.is_perfect(), keyboard.is_perfect());
+        assert_eq!(copy.orientation(), keyboard.orientation());
+        assert_eq!(copy.mirror(), keyboard.mirror());
+        assert_eq!(copy.report().adjustments(), keyboard.report().adjustments());
+        assert_eq!(
+            format!("{:?}", copy.white_keys(true)),
+            format!("{:?}", keyboard.white_keys(true))
+        );
+        assert_eq!(
+            format!("{:?}", copy.black_keys()),
+            format!("{:?}", keyboard.black_keys())
+        );
+        for y in (0..keyboard.height).step_by(7) {
+            for x in 0..keyboard.width {
+                assert_eq!(copy.key_at(x, y), keyboard.key_at(x, y));
+            }
+        }
+        assert_eq!(copy.key_at(keyboard.width, 0), Hit::Outside);
+
+        let keyboard = KeyboardBuilder::new()
+            .fit_into(800, 100, FitStrategy::ShrinkWidth)
+            .unwrap()
+            .build2d();
+        assert_eq!(round_trip(&keyboard).fit(), keyboard.fit());
+
+        let keyboard = KeyboardBuilder::new()
+            .set_key_pattern(KeyPattern::edo31())
+            .unwrap()
+            .build2d();
+        let json = serde_json::to_value(&keyboard).unwrap();
+        assert_eq!(json["pattern"], KeyPattern::edo31().to_string());
+        let copy = round_trip(&keyboard);
+        assert_eq!(copy.key_pattern(), keyboard.key_pattern());
+        assert_eq!(copy.report().adjustments(), keyboard.report().adjustments());
+    }
+    #[test]
+    fn test_inconsistent() {
+        let keyboard = KeyboardBuilder::new().build2d();
+        let mut json = serde_json::to_value(&keyboard).unwrap();
+        json["right_white_key"] = 100.into();
+        assert!(serde_json::from_value::<Keyboard2d>(json.clone()).is_err());
+        json["right_white_key"] = keyboard.right_white_key.into();
+        json["height"] = 1.into();
+        assert!(serde_json::from_value::<Keyboard2d>(json.clone()).is_err());
+        json["height"] = keyboard.height.into();
+        json["pattern"] = "WWBWB".into();
+        assert!(serde_json::from_value::<Keyboard2d>(json.clone()).is_err());
+        json["pattern"] = "WBX".into();
+        assert!(serde_json::from_value::<Keyboard2d>(json).is_err());
+    }
+    #[test]
+    fn test_out_of_bounds() {
+        let keyboard = KeyboardBuilder::new()
+            .set_most_left_right_keys(61, 70)
+            .unwrap()
+            .set_width(200)
+            .unwrap()
+            .build2d();
+        let json = serde_json::to_value(&keyboard).unwrap();
+        assert!(serde_json::from_value::<Keyboard2d>(json.clone()).is_ok());
+
+        let mut wide = json.clone();
+        wide["elements"][0]["BlackKey"][0]["x"] = (keyboard.width - 1).into();
+        let error = serde_json::from_value::<Keyboard2d>(wide).err().unwrap();
+        assert!(error.to_string().contains("exceeds the size"));
+
+        let mut deep = json.clone();
+        deep["blinds"][0]["height"] = (keyboard.height + 1).into();
+        assert!(serde_json::from_value::<Keyboard2d>(deep).is_err());
+    }
+}
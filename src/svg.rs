@@ -0,0 +1,242 @@
+//! SVG output of a Keyboard2d.
+//!
+//! Every key is written as one closed path from Keyboard2d::outline() with an id
+//! derived from the MIDI key code and classes for color, note name and octave.
+//! For example the middle C is:
+//!
+//! `<path id="key-60" class="key white note-C octave-4" data-key="60" d="..."/>`
+//!
+//! For key patterns other than twelve tone the note name is replaced by the step
+//! within the octave, e.g. class="key black step-3 octave-1".
+//!
+//! The colors are written as presentation attributes, so any CSS rule overrides them.
+//! The visible parts of white keys outside of the key range have the class blind.
+use std::fmt::Write as _;
+use std::io;
+
+use crate::{Element, Keyboard2d};
+
+const NOTE_NAMES: [&str; 12] = [
+    "C", "Cs", "D", "Ds", "E", "F", "Fs", "G", "Gs", "A", "As", "B",
+];
+
+/// The style of the SVG output.
+#[derive(Clone, Debug)]
+pub struct SvgStyle {
+    white_fill: String,
+    black_fill: String,
+    stroke: Option<(String, f32)>,
+    gap_fill: Option<String>,
+    id_prefix: String,
+}
+impl Default for SvgStyle {
+    fn default() -> Self {
+        SvgStyle::new()
+    }
+}
+impl SvgStyle {
+    /// White and black keys without stroke and the gaps are left transparent.
+    pub fn new() -> SvgStyle {
+        SvgStyle {
+            white_fill: "#ffffff".to_string(),
+            black_fill: "#000000".to_string(),
+            stroke: None,
+            gap_fill: None,
+            id_prefix: String::new(),
+        }
+    }
+    /// Fill color of the white keys in any SVG color notation.
+    pub fn set_white_fill(mut self, color: &str) -> SvgStyle {
+        self.white_fill = color.to_string();
+        self
+    }
+    /// Fill color of the black keys in any SVG color notation.
+    pub fn set_black_fill(mut self, color: &str) -> SvgStyle {
+        self.black_fill = color.to_string();
+        self
+    }
+    /// Stroke of the key outlines. The stroke is centered on the outline,
+    /// so half of it covers the gap.
+    pub fn set_stroke(mut self, color: &str, width: f32) -> SvgStyle {
+        self.stroke = Some((color.to_string(), width));
+        self
+    }
+    /// Render the gaps by a background rectangle of the full keyboard size with class gap.
+    pub fn set_gap_fill(mut self, color: &str) -> SvgStyle {
+        self.gap_fill = Some(color.to_string());
+        self
+    }
+    /// Prefix of the key ids to allow several keyboards in one document.
+    pub fn set_id_prefix(mut self, prefix: &str) -> SvgStyle {
+        self.id_prefix = prefix.to_string();
+        self
+    }
+}
+
+impl Keyboard2d {
+    /// The keyboard as SVG document with one pixel per user unit.
+    pub fn to_svg(&self, style: &SvgStyle) -> String {
+        let mut svg = String::new();
+        // Writing into a String does not fail
+        let _ = self.format_svg(&mut svg, style);
+        svg
+    }
+    /// Write the SVG document as returned by to_svg().
+    pub fn write_svg<W: io::Write>(&self, writer: &mut W, style: &SvgStyle) -> io::Result<()> {
+        writer.write_all(self.to_svg(style).as_bytes())
+    }
+    fn format_svg(&self, svg: &mut String, style: &SvgStyle) -> std::fmt::Result {
+        writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+            w = self.width,
+            h = self.height
+        )?;
+        if let Some(gap_fill) = style.gap_fill.as_ref() {
+            writeln!(
+                svg,
+                r#"<rect class="gap" x="0" y="0" width="{}" height="{}" fill="{}"/>"#,
+                self.width,
+                self.height,
+                escape(gap_fill)
+            )?;
+        }
+        let stroke = match style.stroke.as_ref() {
+            Some((color, width)) => {
+                format!(r#" stroke="{}" stroke-width="{}""#, escape(color), width)
+            }
+            None => String::new(),
+        };
+
+        writeln!(
+            svg,
+            r#"<g class="white-keys" fill="{}"{}>"#,
+            escape(&style.white_fill),
+            stroke
+        )?;
+        for el in self.iter().filter(|el| el.is_white()) {
+            self.format_key(svg, el, style)?;
+        }
+        for r in self.blinds.iter() {
+            writeln!(
+                svg,
+                r#"<rect class="blind" x="{}" y="{}" width="{}" height="{}"/>"#,
+                r.x, r.y, r.width, r.height
+            )?;
+        }
+        writeln!(svg, "</g>")?;
+
+        writeln!(
+            svg,
+            r#"<g class="black-keys" fill="{}"{}>"#,
+            escape(&style.black_fill),
+            stroke
+        )?;
+        for el in self.iter().filter(|el| !el.is_white()) {
+            self.format_key(svg, el, style)?;
+        }
+        writeln!(svg, "</g>")?;
+        writeln!(svg, "</svg>")
+    }
+    fn format_key(&self, svg: &mut String, el: &Element, style: &SvgStyle) -> std::fmt::Result {
+        let key = el.key();
+        let points = self.outline(key).unwrap_or_default();
+        let mut d = String::new();
+        for (i, (x, y)) in points.iter().enumerate() {
+            write!(d, "{}{} {}", if i == 0 { "M" } else { "L" }, x, y)?;
+        }
+        d.push('Z');
+        let pattern = self.key_pattern();
+        let note = if pattern.is_twelve_tone() {
+            format!("note-{}", NOTE_NAMES[pattern.pitch_class(key) as usize])
+        } else {
+            format!("step-{}", pattern.pitch_class(key))
+        };
+        writeln!(
+            svg,
+            r#"<path id="{}key-{}" class="key {} {} octave-{}" data-key="{}" d="{}"/>"#,
+            escape(&style.id_prefix),
+            key,
+            if el.is_white() { "white" } else { "black" },
+            note,
+            pattern.octave(key),
+            key,
+            d
+        )
+    }
+}
+
+// Escape a string for use within a double quoted attribute
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{KeyPattern, KeyboardBuilder, SvgStyle};
+
+    #[test]
+    fn test_svg() {
+        let keyboard = KeyboardBuilder::new()
+            .set_most_left_right_keys(58, 73)
+            .unwrap()
+            .set_width(400)
+            .unwrap()
+            .build2d();
+        let svg = keyboard.to_svg(&SvgStyle::new());
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert!(svg.contains(r#"width="400""#));
+        assert_eq!(svg.matches("<path ").count(), 16);
+        assert!(svg.contains(r#"id="key-60" class="key white note-C octave-4" data-key="60""#));
+        assert!(svg.contains(r#"id="key-73" class="key black note-Cs octave-5""#));
+        // The keyboard starts with a black key, so a blind is visible
+        assert!(svg.contains(r#"<rect class="blind""#));
+        assert!(!svg.contains("gap"));
+        assert!(!svg.contains("stroke"));
+
+        let style = SvgStyle::new()
+            .set_white_fill("ivory")
+            .set_black_fill("#202020")
+            .set_stroke("gray", 0.5)
+            .set_gap_fill("\"red\"")
+            .set_id_prefix("upper-");
+        let svg = keyboard.to_svg(&style);
+        assert!(
+            svg.contains(r#"<g class="white-keys" fill="ivory" stroke="gray" stroke-width="0.5">"#)
+        );
+        assert!(svg.contains(r##"fill="#202020""##));
+        assert!(svg.contains(r#"<rect class="gap" x="0" y="0" width="400""#));
+        assert!(svg.contains("&quot;red&quot;"));
+        assert!(svg.contains(r#"id="upper-key-60""#));
+
+        let mut out = vec![];
+        keyboard.write_svg(&mut out, &style).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), svg);
+
+        let keyboard = KeyboardBuilder::new()
+            .set_key_pattern(KeyPattern::edo19())
+            .unwrap()
+            .build2d();
+        let svg = keyboard.to_svg(&SvgStyle::new());
+        assert!(svg.contains(r#"id="key-22" class="key white step-3 octave-0""#));
+        assert!(!svg.contains("note-"));
+    }
+    #[test]
+    fn test_svg_paths() {
+        // The path of a key is its outline
+        let keyboard = KeyboardBuilder::new().build2d();
+        let svg = keyboard.to_svg(&SvgStyle::new());
+        for el in keyboard.iter() {
+            let points = keyboard.outline(el.key()).unwrap();
+            let start = format!(r#"id="key-{}""#, el.key());
+            let line = svg.lines().find(|l| l.contains(&start)).unwrap();
+            let (x, y) = points[0];
+            assert!(line.contains(&format!(r#"d="M{} {}L"#, x, y)));
+            assert_eq!(line.matches('L').count(), points.len() - 1);
+        }
+    }
+}
@@ -0,0 +1,496 @@
+//! Terminal output of a Keyboard2d using block characters.
+//!
+//! With half blocks every character cell shows two pixels on top of each other:
+//! the upper pixel as foreground of '▀' and the lower pixel as background.
+//! With quarter blocks a cell shows 2x2 pixels by the quadrant characters, but still
+//! only in two colors, so a third color in a cell is replaced by the closest one.
+//!
+//! A terminal cell is about twice as high as wide. KeyboardBuilder::set_cell_aspect()
+//! sets the dot ratio for the actual cell aspect, and the width is given in pixel
+//! columns, which are one or two per cell.
+//!
+//! The cells are printed with 24 bit ANSI colors by to_ansi(). With the feature
+//! `ratatui` the keyboard is rendered into a ratatui buffer by the KeyboardWidget.
+use std::fmt::Write as _;
+
+use crate::{Keyboard2d, KeyboardBuilder, KeyboardError, RgbaStyle};
+
+const UPPER_HALF_BLOCK: char = '▀';
+
+// The quadrant characters indexed by the foreground pixels:
+// upper left = 1, upper right = 2, lower left = 4, lower right = 8
+const QUADRANTS: [char; 16] = [
+    ' ', '▘', '▝', '▀', '▖', '▌', '▞', '▛', '▗', '▚', '▐', '▜', '▄', '▙', '▟', '█',
+];
+
+/// The block characters used for the pixels of a cell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Blocks {
+    /// One pixel column and two pixel rows per cell
+    Half,
+    /// Two pixel columns and two pixel rows per cell
+    Quarter,
+}
+impl Blocks {
+    /// The number of pixel columns and rows of one cell.
+    pub fn pixels_per_cell(&self) -> (u16, u16) {
+        match self {
+            Blocks::Half => (1, 2),
+            Blocks::Quarter => (2, 2),
+        }
+    }
+}
+
+impl KeyboardBuilder {
+    /// Set the dot ratio for the output as terminal cells with the given blocks.
+    /// The aspect of a cell is given by its width and height, e.g. 1 and 2.
+    ///
+    /// The width of the keyboard is given in pixel columns, so for Blocks::Quarter
+    /// it is twice the number of terminal columns.
+    pub fn set_cell_aspect(
+        self,
+        blocks: Blocks,
+        cell_width: u16,
+        cell_height: u16,
+    ) -> Result<KeyboardBuilder, KeyboardError> {
+        let (columns, rows) = blocks.pixels_per_cell();
+        let dot_width = cell_width as u32 * rows as u32;
+        let dot_height = cell_height as u32 * columns as u32;
+        if dot_width > 65535 || dot_height > 65535 {
+            return Err(KeyboardError::InvalidDotRatio {
+                dot_width: cell_width,
+                dot_height: cell_height,
+            });
+        }
+        self.set_dot_ratio(dot_width as u16, dot_height as u16)
+    }
+}
+
+/// One character cell with RGB colors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cell {
+    /// A block character or ' ', if all pixels have the background color
+    pub symbol: char,
+    pub fg: [u8; 3],
+    pub bg: [u8; 3],
+}
+
+/// The colors for the terminal output and the pressed keys to highlight.
+#[derive(Clone, Debug)]
+pub struct TerminalStyle {
+    background: [u8; 3],
+    white: [u8; 3],
+    black: [u8; 3],
+    pressed_white: [u8; 3],
+    pressed_black: [u8; 3],
+    pressed: [bool; 128],
+    blocks: Blocks,
+}
+impl Default for TerminalStyle {
+    fn default() -> Self {
+        TerminalStyle::new()
+    }
+}
+impl TerminalStyle {
+    /// Gray gaps, white and black keys and pressed keys in blue.
+    pub fn new() -> TerminalStyle {
+        TerminalStyle {
+            background: [96, 96, 96],
+            white: [255, 255, 255],
+            black: [0, 0, 0],
+            pressed_white: [110, 170, 255],
+            pressed_black: [30, 80, 210],
+            pressed: [false; 128],
+            blocks: Blocks::Half,
+        }
+    }
+    /// The color of the gaps and of the area outside of the keys.
+    pub fn set_background(mut self, color: [u8; 3]) -> TerminalStyle {
+        self.background = color;
+        self
+    }
+    pub fn set_white(mut self, color: [u8; 3]) -> TerminalStyle {
+        self.white = color;
+        self
+    }
+    pub fn set_black(mut self, color: [u8; 3]) -> TerminalStyle {
+        self.black = color;
+        self
+    }
+    /// The highlight colors of pressed white and black keys.
+    pub fn set_pressed_colors(mut self, white: [u8; 3], black: [u8; 3]) -> TerminalStyle {
+        self.pressed_white = white;
+        self.pressed_black = black;
+        self
+    }
+    /// Mark a key as pressed or released. Keys out of the MIDI range are ignored.
+    pub fn set_pressed(mut self, key: u8, pressed: bool) -> TerminalStyle {
+        if let Some(p) = self.pressed.get_mut(key as usize) {
+            *p = pressed;
+        }
+        self
+    }
+    /// Use half or quarter blocks. The default are half blocks.
+    pub fn set_blocks(mut self, blocks: Blocks) -> TerminalStyle {
+        self.blocks = blocks;
+        self
+    }
+    /// Release all keys.
+    pub fn clear_pressed(mut self) -> TerminalStyle {
+        self.pressed = [false; 128];
+        self
+    }
+    fn rgba_style(&self, keyboard: &Keyboard2d) -> RgbaStyle {
+        let rgba = |c: [u8; 3]| [c[0], c[1], c[2], 255];
+        let mut style = RgbaStyle::new()
+            .set_background(rgba(self.background))
+            .set_white(rgba(self.white))
+            .set_black(rgba(self.black));
+        for el in keyboard.iter().filter(|el| self.pressed[el.key() as usize]) {
+            let color = if el.is_white() {
+                self.pressed_white
+            } else {
+                self.pressed_black
+            };
+            style = style.set_key_color(el.key(), rgba(color));
+        }
+        style
+    }
+}
+
+impl Keyboard2d {
+    /// The keyboard as rows of cells from top to bottom with one or two pixel columns
+    /// per cell depending on the blocks of the style. Pixels beyond the right or
+    /// bottom edge of the keyboard show the background.
+    pub fn to_cells(&self, style: &TerminalStyle) -> Vec<Vec<Cell>> {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let pixels = self.to_rgba(&style.rgba_style(self));
+        let pixel = |x: usize, y: usize| {
+            if x < width && y < height {
+                let i = 4 * (y * width + x);
+                [pixels[i], pixels[i + 1], pixels[i + 2]]
+            } else {
+                style.background
+            }
+        };
+        let (columns, rows) = style.blocks.pixels_per_cell();
+        let (columns, rows) = (columns as usize, rows as usize);
+        (0..(height + rows - 1) / rows)
+            .map(|row| {
+                (0..(width + columns - 1) / columns)
+                    .map(|column| {
+                        let (x, y) = (column * columns, row * rows);
+                        match style.blocks {
+                            Blocks::Half => {
+                                let fg = pixel(x, y);
+                                let bg = pixel(x, y + 1);
+                                let symbol = if fg == bg { ' ' } else { UPPER_HALF_BLOCK };
+                                Cell { symbol, fg, bg }
+                            }
+                            Blocks::Quarter => quadrant([
+                                pixel(x, y),
+                                pixel(x + 1, y),
+                                pixel(x, y + 1),
+                                pixel(x + 1, y + 1),
+                            ]),
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+    /// The keyboard as lines of text with 24 bit ANSI color codes.
+    /// Every line ends with a reset of the colors and a newline.
+    pub fn to_ansi(&self, style: &TerminalStyle) -> String {
+        let mut ansi = String::new();
+        for row in self.to_cells(style) {
+            let mut last: Option<([u8; 3], [u8; 3])> = None;
+            for cell in row {
+                if last != Some((cell.fg, cell.bg)) {
+                    let (fg, bg) = (cell.fg, cell.bg);
+                    // Writing into a String does not fail
+                    let _ = write!(
+                        ansi,
+                        "\x1b[38;2;{};{};{};48;2;{};{};{}m",
+                        fg[0], fg[1], fg[2], bg[0], bg[1], bg[2]
+                    );
+                    last = Some((fg, bg));
+                }
+                ansi.push(cell.symbol);
+            }
+            ansi.push_str("\x1b[0m\n");
+        }
+        ansi
+    }
+}
+
+// A cell of 2x2 pixels in the order of the bits of QUADRANTS. The most frequent color
+// becomes the background and the next one the foreground. Any further color is
+// shown as the closer one of both.
+fn quadrant(pixels: [[u8; 3]; 4]) -> Cell {
+    let count = |c: &[u8; 3]| pixels.iter().filter(|p| *p == c).count();
+    let mut colors: Vec<[u8; 3]> = vec![];
+    for p in pixels.iter() {
+        if !colors.contains(p) {
+            colors.push(*p);
+        }
+    }
+    // A stable sort keeps the first pixel in front for equal counts
+    colors.sort_by_key(|c| std::cmp::Reverse(count(c)));
+    let bg = colors[0];
+    let fg = match colors.iter().find(|c| **c != bg) {
+        Some(fg) => *fg,
+        None => {
+            return Cell {
+                symbol: ' ',
+                fg: bg,
+                bg,
+            }
+        }
+    };
+    let distance = |a: [u8; 3], b: [u8; 3]| {
+        a.iter()
+            .zip(b.iter())
+            .map(|(a, b)| (*a as i32 - *b as i32).pow(2))
+            .sum::<i32>()
+    };
+    let mut mask = 0;
+    for (i, p) in pixels.iter().enumerate() {
+        if *p == fg || (*p != bg && distance(*p, fg) < distance(*p, bg)) {
+            mask |= 1 << i;
+        }
+    }
+    Cell {
+        symbol: QUADRANTS[mask],
+        fg,
+        bg,
+    }
+}
+
+/// A ratatui widget showing a keyboard with the given style.
+///
+/// The cells are placed at the top left of the area and clipped to it.
+#[cfg(feature = "ratatui")]
+pub struct KeyboardWidget<'a> {
+    keyboard: &'a Keyboard2d,
+    style: &'a TerminalStyle,
+}
+#[cfg(feature = "ratatui")]
+impl Keyboard2d {
+    /// The keyboard as ratatui widget.
+    pub fn widget<'a>(&'a self, style: &'a TerminalStyle) -> KeyboardWidget<'a> {
+        KeyboardWidget {
+            keyboard: self,
+            style,
+        }
+    }
+}
+#[cfg(feature = "ratatui")]
+impl ratatui::widgets::Widget for KeyboardWidget<'_> {
+    fn render(self, area: ratatui::layout::Rect, buf: &mut ratatui::buffer::Buffer) {
+        use ratatui::style::Color;
+        let area = area.intersection(buf.area);
+        let cells = self.keyboard.to_cells(self.style);
+        for (row, y) in cells.iter().zip(area.top()..area.bottom()) {
+            for (cell, x) in row.iter().zip(area.left()..area.right()) {
+                if let Some(target) = buf.cell_mut((x, y)) {
+                    let (fg, bg) = (cell.fg, cell.bg);
+                    target
+                        .set_char(cell.symbol)
+                        .set_fg(Color::Rgb(fg[0], fg[1], fg[2]))
+                        .set_bg(Color::Rgb(bg[0], bg[1], bg[2]));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Blocks, Hit, KeyboardBuilder, TerminalStyle};
+
+    #[test]
+    fn test_cells() {
+        let keyboard = KeyboardBuilder::new()
+            .set_most_left_right_keys(48, 72)
+            .unwrap()
+            .set_width(80)
+            .unwrap()
+            .build2d();
+        let style = TerminalStyle::new()
+            .set_pressed(60, true)
+            .set_pressed(61, true);
+        let cells = keyboard.to_cells(&style);
+        assert_eq!(cells.len(), (keyboard.height as usize + 1) / 2);
+        assert!(cells.iter().all(|row| row.len() == 80));
+
+        let pixel = |x: u16, y: u16| {
+            let cell = cells[y as usize / 2][x as usize];
+            if y % 2 == 0 {
+                cell.fg
+            } else {
+                cell.bg
+            }
+        };
+        for y in 0..keyboard.height {
+            for x in 0..keyboard.width {
+                let expected = match keyboard.key_at(x, y) {
+                    Hit::Key(60) => [110, 170, 255],
+                    Hit::Key(61) => [30, 80, 210],
+                    Hit::Key(key) if KeyboardBuilder::is_white(key) => [255, 255, 255],
+                    Hit::Key(_) => [0, 0, 0],
+                    _ => [96, 96, 96],
+                };
+                assert_eq!(pixel(x, y), expected);
+            }
+        }
+        for cell in cells.iter().flatten() {
+            assert_eq!(cell.symbol == ' ', cell.fg == cell.bg);
+        }
+
+        let released = keyboard.to_cells(&style.clone().clear_pressed());
+        assert_ne!(released, cells);
+        assert_eq!(
+            released,
+            keyboard.to_cells(&style.set_pressed(60, false).set_pressed(61, false))
+        );
+    }
+    #[test]
+    fn test_ansi() {
+        let keyboard = KeyboardBuilder::new()
+            .set_most_left_right_keys(60, 71)
+            .unwrap()
+            .set_width(41)
+            .unwrap()
+            .build2d();
+        let style = TerminalStyle::new().set_pressed(64, true);
+        let ansi = keyboard.to_ansi(&style);
+        assert!(ansi.contains("\x1b[38;2;0;0;0;48;2;0;0;0m"));
+        assert!(ansi.contains("\x1b[38;2;110;170;255;48;2;110;170;255m"));
+        // Without the color codes the text consists of the cell symbols
+        let cells = keyboard.to_cells(&style);
+        assert_eq!(ansi.lines().count(), cells.len());
+        for (line, row) in ansi.lines().zip(cells.iter()) {
+            assert!(line.ends_with("\x1b[0m"));
+            let text: String = line
+                .split('\x1b')
+                .map(|s| s.split_once('m').map(|(_, t)| t).unwrap_or(s))
+                .collect();
+            let symbols: String = row.iter().map(|c| c.symbol).collect();
+            assert_eq!(text, symbols);
+        }
+    }
+    #[test]
+    fn test_quarter_blocks() {
+        let keyboard = KeyboardBuilder::new()
+            .set_most_left_right_keys(48, 72)
+            .unwrap()
+            .set_width(121)
+            .unwrap()
+            .build2d();
+        let style = TerminalStyle::new()
+            .set_blocks(Blocks::Quarter)
+            .set_pressed(64, true);
+        let cells = keyboard.to_cells(&style);
+        assert_eq!(cells.len(), (keyboard.height as usize + 1) / 2);
+        assert!(cells.iter().all(|row| row.len() == 61));
+
+        // Cells with up to two colors show the pixels exactly
+        let half = keyboard.to_cells(&style.clone().set_blocks(Blocks::Half));
+        let pixel = |x: usize, y: usize| {
+            let cell = half[y / 2][x];
+            if y % 2 == 0 {
+                cell.fg
+            } else {
+                cell.bg
+            }
+        };
+        let mut exact = 0;
+        for (row, cells) in cells.iter().enumerate() {
+            for (column, cell) in cells.iter().enumerate() {
+                let mut pixels = vec![];
+                for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let (x, y) = (2 * column + dx, 2 * row + dy);
+                    if x < keyboard.width as usize && y < 2 * half.len() {
+                        pixels.push((dx + 2 * dy, pixel(x, y)));
+                    }
+                }
+                let mut colors = pixels.iter().map(|(_, c)| *c).collect::<Vec<_>>();
+                colors.sort();
+                colors.dedup();
+                if colors.len() <= 2 {
+                    exact += 1;
+                    let mask = super::QUADRANTS
+                        .iter()
+                        .position(|c| *c == cell.symbol)
+                        .unwrap();
+                    for (bit, color) in pixels {
+                        let expected = if mask & (1 << bit) != 0 {
+                            cell.fg
+                        } else {
+                            cell.bg
+                        };
+                        assert_eq!(color, expected);
+                    }
+                }
+            }
+        }
+        assert!(exact * 10 > 61 * cells.len() * 9);
+        assert!(cells.iter().flatten().all(|c| c.symbol != '█'));
+    }
+    #[test]
+    fn test_cell_aspect() {
+        let builder = KeyboardBuilder::new().set_width(400).unwrap();
+        let square = builder
+            .clone()
+            .set_cell_aspect(Blocks::Half, 1, 2)
+            .unwrap()
+            .build2d();
+        assert_eq!(square.height, builder.clone().build2d().height);
+        let quarter = builder
+            .clone()
+            .set_cell_aspect(Blocks::Quarter, 1, 2)
+            .unwrap()
+            .build2d();
+        let tall = builder.set_dot_ratio(1, 2).unwrap().build2d();
+        assert_eq!(quarter.height, tall.height);
+        assert!(KeyboardBuilder::new()
+            .set_cell_aspect(Blocks::Quarter, 0, 2)
+            .is_err());
+    }
+    #[cfg(feature = "ratatui")]
+    #[test]
+    fn test_widget() {
+        use ratatui::buffer::Buffer;
+        use ratatui::layout::Rect;
+        use ratatui::style::Color;
+        use ratatui::widgets::Widget;
+
+        let keyboard = KeyboardBuilder::new()
+            .set_most_left_right_keys(60, 71)
+            .unwrap()
+            .set_width(41)
+            .unwrap()
+            .build2d();
+        let style = TerminalStyle::new().set_pressed(62, true);
+        let cells = keyboard.to_cells(&style);
+        let area = Rect::new(2, 1, 30, 4);
+        let mut buf = Buffer::empty(Rect::new(0, 0, 40, 10));
+        keyboard.widget(&style).render(area, &mut buf);
+        for y in 0..10 {
+            for x in 0..40 {
+                let target = &buf[(x, y)];
+                if area.contains((x, y).into()) {
+                    let cell = cells[(y - 1) as usize][(x - 2) as usize];
+                    let (fg, bg) = (cell.fg, cell.bg);
+                    assert_eq!(target.symbol(), cell.symbol.to_string());
+                    assert_eq!(target.fg, Color::Rgb(fg[0], fg[1], fg[2]));
+                    assert_eq!(target.bg, Color::Rgb(bg[0], bg[1], bg[2]));
+                } else {
+                    assert_eq!(target.symbol(), " ");
+                    assert_eq!(target.fg, Color::Reset);
+                }
+            }
+        }
+    }
+}
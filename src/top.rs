@@ -1,6 +1,6 @@
 use crate::base::*;
 use crate::Base;
-use crate::KeyboardBuilder;
+use crate::{KeyboardBuilder, KeyboardError};
 
 #[derive(Debug)]
 pub enum TopResultElement {
@@ -35,14 +35,14 @@ pub struct Top {
     b_left_blind_width: u16,
 }
 impl Top {
-    pub fn calculate(kb: &KeyboardBuilder, base: &Base) -> Top {
+    pub fn calculate(kb: &KeyboardBuilder, base: &Base) -> Result<Top, KeyboardError> {
         let mut top = Top::default();
 
         top.kb_width_min = base.get_black_key_min_width();
         top.cde_pars = base.get_cde_pars();
-        top.cde_width = top.cde_pars.iter().sum();
+        top.cde_width = sum(kb, &top.cde_pars)?;
         top.fgab_pars = base.get_fgab_pars();
-        top.fgab_width = top.fgab_pars.iter().sum();
+        top.fgab_width = sum(kb, &top.fgab_pars)?;
         if kb.need_black_gap {
             top.cde_gap = base.get_cde_gap();
             top.fgab_gap = base.get_fgab_gap();
@@ -56,14 +56,13 @@ impl Top {
         //
         // In order to have same size white keys, multiple of three should be ensured.
 
-        top.cde_black_key_width = match (top.cde_width - 2 * top.kb_width_min - 4 * top.cde_gap) % 3
-        {
-            0 => top.kb_width_min,
-            1 => top.kb_width_min + 2,
-            2 => top.kb_width_min + 1,
-            _ => panic!("cannot happen"),
-        };
-        top.cde_key_width = (top.cde_width - 2 * top.cde_black_key_width - 4 * top.cde_gap) / 3;
+        top.cde_black_key_width =
+            match sub(top.cde_width, 2 * top.kb_width_min + 4 * top.cde_gap)? % 3 {
+                0 => top.kb_width_min,
+                1 => top.kb_width_min + 2,
+                _ => top.kb_width_min + 1,
+            };
+        top.cde_key_width = sub(top.cde_width, 2 * top.cde_black_key_width + 4 * top.cde_gap)? / 3;
 
         // fgab-part
         // This contains three black keys and six gaps (optionally).
@@ -78,18 +77,24 @@ impl Top {
             (false, true) => top.cde_black_key_width + 1,
             (false, false) => top.cde_black_key_width,
         };
-        let fgab_white_width =
-            top.fgab_width - 2 * top.black_fs_as_width - top.black_gs_width - 6 * top.fgab_gap;
-
-        assert!(fgab_white_width % 2 == 0);
+        let fgab_white_width = sub(
+            top.fgab_width,
+            2 * top.black_fs_as_width + top.black_gs_width + 6 * top.fgab_gap,
+        )?;
+
+        if fgab_white_width % 2 != 0 {
+            return Err(KeyboardError::InconsistentLayout(
+                "white keys f,g,a,b have odd width",
+            ));
+        }
 
         // The distribution of width on the pairs g/a and f/b should be according to the um
         // In case fgab_width is not multiple of two, then f/b should be smaller than g/a
-        let ga_white_width = ((fgab_white_width as u32 * kb.white_key_small_width_ga_10um as u32)
-            / (kb.white_key_small_width_ga_10um + kb.white_key_small_width_fb_10um) as u32)
+        let ga_white_width = ((fgab_white_width as u32 * kb.dims.white_key_small_width_ga_10um)
+            / (kb.dims.white_key_small_width_ga_10um + kb.dims.white_key_small_width_fb_10um))
             as u16;
-        let fb_white_width = ((fgab_white_width as u32 * kb.white_key_small_width_fb_10um as u32)
-            / (kb.white_key_small_width_ga_10um + kb.white_key_small_width_fb_10um) as u32)
+        let fb_white_width = ((fgab_white_width as u32 * kb.dims.white_key_small_width_fb_10um)
+            / (kb.dims.white_key_small_width_ga_10um + kb.dims.white_key_small_width_fb_10um))
             as u16;
         let (ga_white_width, fb_white_width) = match (
             fgab_white_width - (ga_white_width + fb_white_width),
@@ -103,88 +108,127 @@ impl Top {
             (1, false) => (ga_white_width, fb_white_width + 1),
             (2, false) => (ga_white_width + 1, fb_white_width + 1),
             (3, false) => (ga_white_width + 2, fb_white_width + 1),
-            _ => panic!("Should not happen"),
+            _ => {
+                return Err(KeyboardError::InconsistentLayout(
+                    "white keys f,g,a,b cannot be split",
+                ))
+            }
         };
 
         top.ga_white_width = ga_white_width;
         top.fb_white_width = fb_white_width;
 
-        top.d_left_blind_width = top.cde_key_width + 2 * top.cde_gap + top.cde_black_key_width
-            - top.cde_pars[0..=1].iter().sum::<u16>();
-        top.e_left_blind_width =
-            2 * top.cde_key_width + 4 * top.cde_gap + 2 * top.cde_black_key_width
-                - top.cde_pars[0..=3].iter().sum::<u16>();
-
-        top.g_left_blind_width = top.fb_white_width / 2 + 2 * top.fgab_gap + top.black_fs_as_width
-            - top.fgab_pars[0..=1].iter().sum::<u16>();
-        top.a_left_blind_width = top.fb_white_width / 2
-            + 4 * top.fgab_gap
-            + top.black_fs_as_width
-            + top.ga_white_width / 2
-            + top.black_gs_width
-            - top.fgab_pars[0..=3].iter().sum::<u16>();
-        top.b_left_blind_width = top.fb_white_width / 2
-            + 6 * top.fgab_gap
-            + 2 * top.black_fs_as_width
-            + top.ga_white_width
-            + top.black_gs_width
-            - top.fgab_pars[0..=5].iter().sum::<u16>();
-
-        top
+        top.d_left_blind_width = sub(
+            top.cde_key_width + 2 * top.cde_gap + top.cde_black_key_width,
+            top.cde_pars[0..=1].iter().sum::<u16>(),
+        )?;
+        top.e_left_blind_width = sub(
+            2 * top.cde_key_width + 4 * top.cde_gap + 2 * top.cde_black_key_width,
+            top.cde_pars[0..=3].iter().sum::<u16>(),
+        )?;
+
+        top.g_left_blind_width = sub(
+            top.fb_white_width / 2 + 2 * top.fgab_gap + top.black_fs_as_width,
+            top.fgab_pars[0..=1].iter().sum::<u16>(),
+        )?;
+        top.a_left_blind_width = sub(
+            top.fb_white_width / 2
+                + 4 * top.fgab_gap
+                + top.black_fs_as_width
+                + top.ga_white_width / 2
+                + top.black_gs_width,
+            top.fgab_pars[0..=3].iter().sum::<u16>(),
+        )?;
+        top.b_left_blind_width = sub(
+            top.fb_white_width / 2
+                + 6 * top.fgab_gap
+                + 2 * top.black_fs_as_width
+                + top.ga_white_width
+                + top.black_gs_width,
+            top.fgab_pars[0..=5].iter().sum::<u16>(),
+        )?;
+
+        Ok(top)
+    }
+    pub fn black_key_width(&self) -> u16 {
+        self.black_fs_as_width
     }
-    pub fn is_perfect(&self) -> bool {
-        self.black_fs_as_width == self.black_gs_width
+    // Difference in width of g# to f#/a#
+    pub fn black_gs_enlarged(&self) -> u16 {
+        self.black_gs_width.saturating_sub(self.black_fs_as_width)
     }
-    pub fn get_top_for(&self, el: &ResultElement) -> TopResultElement {
+    pub fn get_top_for(&self, el: &ResultElement) -> Result<TopResultElement, KeyboardError> {
         use crate::TopResultElement::*;
         match el {
             ResultElement::Key(width, key) => {
-                // The correction is needed for alternating key d size
-                let corr = match key % 12 {
-                    KEY_C => width - self.cde_pars[0],
-                    KEY_D => width - self.cde_pars[2],
-                    KEY_E => width - self.cde_pars[4],
-                    KEY_F => width - self.fgab_pars[0],
-                    KEY_G => width - self.fgab_pars[2],
-                    KEY_A => width - self.fgab_pars[4],
-                    KEY_B => width - self.fgab_pars[6],
-                    _ => 0,
+                // The correction is needed for alternating key d size.
+                // It can be negative for a key at the edge, which has not been enlarged.
+                let reference = match key % 12 {
+                    KEY_C => self.cde_pars[0],
+                    KEY_D => self.cde_pars[2],
+                    KEY_E => self.cde_pars[4],
+                    KEY_F => self.fgab_pars[0],
+                    KEY_G => self.fgab_pars[2],
+                    KEY_A => self.fgab_pars[4],
+                    KEY_B => self.fgab_pars[6],
+                    _ => *width,
                 };
-                match key % 12 {
+                let corrected = |w: u16| (w + width).saturating_sub(reference);
+                Ok(match key % 12 {
                     KEY_C => WhiteGapBlack(
-                        self.cde_key_width + corr,
+                        corrected(self.cde_key_width),
                         self.cde_gap,
                         self.cde_black_key_width,
                     ),
                     KEY_D => BlindWhiteGapBlack(
                         self.d_left_blind_width,
-                        self.cde_key_width + corr,
+                        corrected(self.cde_key_width),
                         self.cde_gap,
                         self.cde_black_key_width,
                     ),
-                    KEY_E => BlindWhite(self.e_left_blind_width, self.cde_key_width + corr),
+                    KEY_E => BlindWhite(self.e_left_blind_width, corrected(self.cde_key_width)),
                     KEY_F => WhiteGapBlack(
-                        self.fb_white_width / 2 + corr,
+                        corrected(self.fb_white_width / 2),
                         self.fgab_gap,
                         self.black_fs_as_width,
                     ),
                     KEY_G => BlindWhiteGapBlack(
                         self.g_left_blind_width,
-                        self.ga_white_width / 2 + corr,
+                        corrected(self.ga_white_width / 2),
                         self.cde_gap,
                         self.black_gs_width,
                     ),
                     KEY_A => BlindWhiteGapBlack(
                         self.a_left_blind_width,
-                        self.ga_white_width / 2 + corr,
+                        corrected(self.ga_white_width / 2),
                         self.cde_gap,
                         self.black_fs_as_width,
                     ),
-                    KEY_B => BlindWhite(self.b_left_blind_width, self.fb_white_width / 2 + corr),
-                    _ => panic!("Should not happen"),
-                }
+                    KEY_B => {
+                        BlindWhite(self.b_left_blind_width, corrected(self.fb_white_width / 2))
+                    }
+                    _ => return Err(KeyboardError::InconsistentLayout("black key in base")),
+                })
             }
-            ResultElement::Gap(_) => panic!("Do not call with Gap"),
+            ResultElement::Gap(_) => Err(KeyboardError::InconsistentLayout("gap has no top")),
         }
     }
 }
+
+// The black keys and gaps must fit into the width of the white keys
+// The parts of an octave may exceed u16 for very wide keys, e.g. a single key keyboard.
+fn sum(kb: &KeyboardBuilder, pars: &[u16]) -> Result<u16, KeyboardError> {
+    let width = pars.iter().map(|w| *w as u32).sum::<u32>();
+    if width > 65535 {
+        return Err(KeyboardError::WidthTooBig {
+            width: kb.width,
+            max_width: kb.layout_max_width(),
+        });
+    }
+    Ok(width as u16)
+}
+
+fn sub(a: u16, b: u16) -> Result<u16, KeyboardError> {
+    a.checked_sub(b)
+        .ok_or(KeyboardError::InconsistentLayout("black keys do not fit"))
+}
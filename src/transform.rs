@@ -0,0 +1,169 @@
+//! Mapping of the horizontal keyboard layout into the requested orientation.
+//!
+//! All layout calculations are performed for a horizontal keyboard with the low keys
+//! on the left and the back of the keys at the top. This is called the canonical layout.
+//! The transform maps the canonical coordinates to the output coordinates and back.
+//! Mirroring is applied after the orientation on the output coordinates.
+use crate::{Rectangle, RectangleF32};
+
+/// The orientation of the keyboard.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Orientation {
+    /// Low keys on the left, the back of the keys (black keys) at the top.
+    #[default]
+    Horizontal,
+    /// Low keys at the bottom, the back of the keys (black keys) at the left.
+    VerticalLowAtBottom,
+    /// Low keys at the top, the back of the keys (black keys) at the left.
+    VerticalLowAtTop,
+}
+
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Transform {
+    orientation: Orientation,
+    mirror_horizontal: bool,
+    mirror_vertical: bool,
+    // Size of the canonical layout
+    length: u16,
+    depth: u16,
+}
+impl Transform {
+    pub fn new(orientation: Orientation, length: u16, depth: u16) -> Transform {
+        Transform {
+            orientation,
+            length,
+            depth,
+            ..Default::default()
+        }
+    }
+    pub fn mirrored(mut self, horizontal: bool, vertical: bool) -> Transform {
+        self.mirror_horizontal = horizontal;
+        self.mirror_vertical = vertical;
+        self
+    }
+    pub fn mirror(&self) -> (bool, bool) {
+        (self.mirror_horizontal, self.mirror_vertical)
+    }
+    pub fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+    pub fn is_vertical(&self) -> bool {
+        self.orientation != Orientation::Horizontal
+    }
+    /// Size of the output as width and height
+    pub fn size(&self) -> (u16, u16) {
+        if self.is_vertical() {
+            (self.depth, self.length)
+        } else {
+            (self.length, self.depth)
+        }
+    }
+    pub fn rect(&self, r: &Rectangle) -> Rectangle {
+        self.flip(&self.rotate(r))
+    }
+    /// Maps a corner between pixels, e.g. the vertex of a polygon.
+    pub fn point(&self, x: u16, y: u16) -> (u16, u16) {
+        let r = self.rect(&Rectangle {
+            x,
+            y,
+            width: 0,
+            height: 0,
+        });
+        (r.x, r.y)
+    }
+    pub fn inverse_rect(&self, r: &Rectangle) -> Rectangle {
+        self.inverse_rotate(&self.flip(r))
+    }
+    /// Same as rect() for fractional geometry.
+    /// The size of the canonical layout is given, because it is not known as integer.
+    pub fn rect_f32(&self, r: &RectangleF32, length: f32, depth: f32) -> RectangleF32 {
+        let r = match self.orientation {
+            Orientation::Horizontal => r.clone(),
+            Orientation::VerticalLowAtBottom => RectangleF32 {
+                x: r.y,
+                y: length - r.x - r.width,
+                width: r.height,
+                height: r.width,
+            },
+            Orientation::VerticalLowAtTop => RectangleF32 {
+                x: r.y,
+                y: r.x,
+                width: r.height,
+                height: r.width,
+            },
+        };
+        let (width, height) = if self.is_vertical() {
+            (depth, length)
+        } else {
+            (length, depth)
+        };
+        RectangleF32 {
+            x: if self.mirror_horizontal {
+                width - r.x - r.width
+            } else {
+                r.x
+            },
+            y: if self.mirror_vertical {
+                height - r.y - r.height
+            } else {
+                r.y
+            },
+            width: r.width,
+            height: r.height,
+        }
+    }
+    // Mirroring is its own inverse
+    fn flip(&self, r: &Rectangle) -> Rectangle {
+        let (width, height) = self.size();
+        Rectangle {
+            x: if self.mirror_horizontal {
+                width - r.x - r.width
+            } else {
+                r.x
+            },
+            y: if self.mirror_vertical {
+                height - r.y - r.height
+            } else {
+                r.y
+            },
+            width: r.width,
+            height: r.height,
+        }
+    }
+    fn rotate(&self, r: &Rectangle) -> Rectangle {
+        match self.orientation {
+            Orientation::Horizontal => r.clone(),
+            Orientation::VerticalLowAtBottom => Rectangle {
+                x: r.y,
+                y: self.length - r.x - r.width,
+                width: r.height,
+                height: r.width,
+            },
+            Orientation::VerticalLowAtTop => Rectangle {
+                x: r.y,
+                y: r.x,
+                width: r.height,
+                height: r.width,
+            },
+        }
+    }
+    fn inverse_rotate(&self, r: &Rectangle) -> Rectangle {
+        match self.orientation {
+            Orientation::Horizontal => r.clone(),
+            Orientation::VerticalLowAtBottom => Rectangle {
+                x: self.length - r.y - r.height,
+                y: r.x,
+                width: r.height,
+                height: r.width,
+            },
+            Orientation::VerticalLowAtTop => Rectangle {
+                x: r.y,
+                y: r.x,
+                width: r.height,
+                height: r.width,
+            },
+        }
+    }
+}